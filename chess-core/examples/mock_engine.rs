@@ -0,0 +1,62 @@
+// a scripted uci engine for the tests, the first argument picks how it misbehaves:
+// "normal" plays the first legal move, "illegal" answers with a move that isn't legal,
+// "crash" exits when asked to search, "hang" never answers a search and "mute" never answers `uci`
+
+use std::io::{self, BufRead, Write};
+
+use chess_core::*;
+
+fn main() {
+    let mode = std::env::args().nth(1).unwrap_or_else(|| "normal".to_string());
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let mut position = Position::new();
+
+    for line in stdin.lock().lines() {
+        let line = line.unwrap();
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("uci") => {
+                if mode == "mute" {
+                    continue;
+                }
+                writeln!(stdout, "id name Mock Engine").unwrap();
+                writeln!(stdout, "id author chess-core tests").unwrap();
+                writeln!(stdout, "option name Hash type spin default 16 min 1 max 1024").unwrap();
+                writeln!(stdout, "option name Clear Hash type button").unwrap();
+                writeln!(stdout, "uciok").unwrap();
+            }
+            Some("isready") => writeln!(stdout, "readyok").unwrap(),
+            Some("position") => {
+                let rest: Vec<&str> = words.collect();
+                let moves_at = rest.iter().position(|word| *word == "moves").unwrap_or(rest.len());
+                position = match rest.first() {
+                    Some(&"fen") => Position::from_fen(&rest[1..moves_at].join(" ")).unwrap(),
+                    _ => Position::new()
+                };
+                for uci in rest.iter().skip(moves_at + 1) {
+                    let mv = position.parse_uci(uci).unwrap();
+                    position.make_move(mv);
+                }
+            }
+            Some("go") => match mode.as_str() {
+                "crash" => std::process::exit(1),
+                "hang" => {}
+                "illegal" => writeln!(stdout, "bestmove a1a8").unwrap(),
+                _ => {
+                    let moves = position.legal_moves();
+                    match moves.first() {
+                        Some(mv) => {
+                            writeln!(stdout, "info depth 1 seldepth 2 score cp 13 nodes 20 nps 1000 time 20 pv {}", mv).unwrap();
+                            writeln!(stdout, "bestmove {}", mv).unwrap();
+                        }
+                        None => writeln!(stdout, "bestmove (none)").unwrap()
+                    }
+                }
+            },
+            Some("quit") => break,
+            _ => {}
+        }
+        stdout.flush().unwrap();
+    }
+}
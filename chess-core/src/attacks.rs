@@ -0,0 +1,217 @@
+use std::sync::OnceLock;
+
+use crate::{bitboard::*, types::*};
+
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_OFFSETS: [(i8, i8); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+// found by trying sparse random numbers until one maps every blocker combination on the rays
+// of the square to a slot with the right attacks
+const BISHOP_MAGICS: [u64; 64] = [
+    0x10102002004a1420, 0x8020040400584008, 0x10510800811201c8, 0x5204042080000088,
+    0x2204106880000002, 0x1401042004000000, 0x0400880410042004, 0x0028208200a02020,
+    0x1500241990010e00, 0x8001200182020a40, 0x40004101030b0000, 0x8002041042000100,
+    0x4010011041020038, 0x0000010421044000, 0x1500210808020a00, 0x8000088400880520,
+    0x0405004010040100, 0x1005823210040108, 0x2708008102040011, 0x4048200404009100,
+    0x0018104101400024, 0x0003000601190101, 0x8004803108491000, 0x8014241200820800,
+    0x0006e080100c3040, 0x0501044a11041800, 0x9020300008004045, 0x0894080000220040,
+    0x1001010083104000, 0x5004030040900080, 0x000400422c012400, 0x0002128698404812,
+    0x1010108404900440, 0x0928021182084100, 0x2006080409020024, 0x1010202020180080,
+    0xa010008200202200, 0x2098015100019004, 0x0002041440810811, 0x802a02020000b098,
+    0x0009015090004060, 0x4000821082081001, 0x0100210040420800, 0x0800004010488a00,
+    0x2000081104004040, 0x4c8e029015000082, 0x0420340322224842, 0x1298260043400210,
+    0x0000822802400008, 0x00008a0101600000, 0x3040003412080021, 0x3040290220884800,
+    0x4a1500401041004a, 0x8010200282020781, 0x0020203142209091, 0x0070300600902110,
+    0x0040808800b62048, 0x0000810400c44420, 0x00080400440c0441, 0x8340080020840411,
+    0x0000000104208200, 0x0000800810d00080, 0x0400530411080200, 0x4040702400932244,
+];
+
+const ROOK_MAGICS: [u64; 64] = [
+    0x1080004008801020, 0x0840092002c03000, 0x1900200010400900, 0x0880100008000480,
+    0x4200100420080200, 0x8100020100080400, 0x0200040110886200, 0x0200008040220411,
+    0x0404800084400220, 0x0000401000402000, 0x0086001081220440, 0x0408800800100280,
+    0x000a001201040820, 0x8848800200840080, 0x4001000100040200, 0x0442000102105084,
+    0x9080010020804100, 0x0040404000201009, 0x0000808010002009, 0x2200090021d00100,
+    0x0008008008040080, 0x0004004002010040, 0x0011040008015042, 0x00000a0001768104,
+    0x0000800080204009, 0x2010004140002001, 0x9800200280100080, 0x1000100080080080,
+    0x0442000a00049020, 0x2100040080020080, 0x0800120400900148, 0x0010040a00128541,
+    0x2800804000800030, 0x1010002000400041, 0x4000200011004100, 0x0610008410800800,
+    0x0400802402800800, 0xc100020080800400, 0x0002000802000401, 0x0182085882000401,
+    0x0220204000808000, 0x2860100040024022, 0x0001002004110040, 0x99101042000a0020,
+    0x0004080004008080, 0x0010040002008080, 0x2012004881020004, 0x8300842444820011,
+    0x0088403882010200, 0x0820400080210100, 0x0110910040a00300, 0x0801100280080480,
+    0x0242009008200600, 0x1002000489500200, 0x0040800200010080, 0x0091800041000080,
+    0x0000209300488001, 0x04c1002414824001, 0x020020000b001041, 0x7000100004200901,
+    0x8002002004100802, 0x30010002084c0007, 0x0888221800813004, 0x4000002840840112,
+];
+
+// squares a knight on the square attacks
+pub fn knight_attacks(square: Square) -> Bitboard {
+    tables().knight[square.index()]
+}
+
+pub fn king_attacks(square: Square) -> Bitboard {
+    tables().king[square.index()]
+}
+
+// squares a pawn of the given color on the square takes on
+pub fn pawn_attacks(color: PieceColor, square: Square) -> Bitboard {
+    tables().pawn[color as usize][square.index()]
+}
+
+// squares a bishop on the square reaches, including the first piece on every diagonal
+pub fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.sliding[tables.bishop[square.index()].index(occupied)]
+}
+
+pub fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.sliding[tables.rook[square.index()].index(occupied)]
+}
+
+pub fn queen_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    bishop_attacks(square, occupied) | rook_attacks(square, occupied)
+}
+
+// squares strictly between the two squares, empty if they aren't on a line
+pub fn between(from: Square, to: Square) -> Bitboard {
+    tables().between[from.index() * 64 + to.index()]
+}
+
+// multiplying the occupied squares on the rays with the magic number packs them into the
+// highest bits, they are the index of the attacks in the sliding table
+struct Magic {
+    // squares on the rays that can block, without the edge of the board
+    mask: Bitboard,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupied: Bitboard) -> usize {
+        ((occupied & self.mask).0.wrapping_mul(self.magic) >> self.shift) as usize + self.offset
+    }
+}
+
+// computed on first use
+struct Tables {
+    knight: [Bitboard; 64],
+    king: [Bitboard; 64],
+    pawn: [[Bitboard; 64]; 2],
+    between: Vec<Bitboard>,
+    bishop: Vec<Magic>,
+    rook: Vec<Magic>,
+    sliding: Vec<Bitboard>,
+}
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+fn tables() -> &'static Tables {
+    TABLES.get_or_init(Tables::new)
+}
+
+impl Tables {
+    fn new() -> Tables {
+        let step_attacks = |offsets: &[(i8, i8)]| {
+            let mut attacks = [Bitboard::EMPTY; 64];
+            for square in Square::all() {
+                for (rank, file) in offsets {
+                    if let Some(to) = square.offset(*rank, *file) {
+                        attacks[square.index()] |= Bitboard::from_square(to);
+                    }
+                }
+            }
+            attacks
+        };
+        let white_pawn = PieceColor::White.pawn_direction();
+        let black_pawn = PieceColor::Black.pawn_direction();
+
+        let mut sliding = Vec::new();
+        let bishop = Square::all().map(|square| fill_magic(square, &BISHOP_DIRECTIONS, BISHOP_MAGICS[square.index()], &mut sliding)).collect();
+        let rook = Square::all().map(|square| fill_magic(square, &ROOK_DIRECTIONS, ROOK_MAGICS[square.index()], &mut sliding)).collect();
+
+        Tables {
+            knight: step_attacks(&KNIGHT_OFFSETS),
+            king: step_attacks(&KING_OFFSETS),
+            pawn: [step_attacks(&[(white_pawn, -1), (white_pawn, 1)]), step_attacks(&[(black_pawn, -1), (black_pawn, 1)])],
+            between: between_table(),
+            bishop,
+            rook,
+            sliding,
+        }
+    }
+}
+
+fn between_table() -> Vec<Bitboard> {
+    let mut between = vec![Bitboard::EMPTY; 64 * 64];
+    for from in Square::all() {
+        for (rank, file) in KING_OFFSETS.iter() {
+            let mut squares = Bitboard::EMPTY;
+            let mut current = from.offset(*rank, *file);
+            while let Some(to) = current {
+                between[from.index() * 64 + to.index()] = squares;
+                squares |= Bitboard::from_square(to);
+                current = to.offset(*rank, *file);
+            }
+        }
+    }
+    between
+}
+
+// walks the rays square by square, only used to fill the tables
+fn ray_attacks(square: Square, directions: &[(i8, i8)], occupied: Bitboard) -> Bitboard {
+    let mut attacks = Bitboard::EMPTY;
+    for (rank, file) in directions {
+        let mut current = square.offset(*rank, *file);
+        while let Some(to) = current {
+            attacks |= Bitboard::from_square(to);
+            if occupied.contains(to) {
+                break;
+            }
+            current = to.offset(*rank, *file);
+        }
+    }
+    attacks
+}
+
+// the last square of a ray can't block anything behind it, so it isn't part of the mask
+fn ray_mask(square: Square, directions: &[(i8, i8)]) -> Bitboard {
+    let mut mask = Bitboard::EMPTY;
+    for (rank, file) in directions {
+        let mut current = square.offset(*rank, *file);
+        while let Some(to) = current {
+            current = to.offset(*rank, *file);
+            if current.is_some() {
+                mask |= Bitboard::from_square(to);
+            }
+        }
+    }
+    mask
+}
+
+// appends the attacks for every blocker combination on the rays to the sliding table
+fn fill_magic(square: Square, directions: &[(i8, i8)], magic: u64, sliding: &mut Vec<Bitboard>) -> Magic {
+    let mask = ray_mask(square, directions);
+    let shift = 64 - mask.count();
+    let offset = sliding.len();
+    sliding.resize(offset + (1 << mask.count()), Bitboard::EMPTY);
+
+    // walk through all subsets of the mask
+    let mut subset = 0u64;
+    loop {
+        let slot = offset + (subset.wrapping_mul(magic) >> shift) as usize;
+        let attacks = ray_attacks(square, directions, Bitboard(subset));
+        debug_assert!(sliding[slot].is_empty() || sliding[slot] == attacks, "bad magic for {}", square);
+        sliding[slot] = attacks;
+
+        subset = subset.wrapping_sub(mask.0) & mask.0;
+        if subset == 0 {
+            break;
+        }
+    }
+    Magic { mask, magic, shift, offset }
+}
@@ -0,0 +1,260 @@
+// plays with the search of chess-core over the universal chess interface on stdin and stdout,
+// so chess guis like cute chess or arena and tournament runners can use it as an engine
+
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use chess_core::*;
+
+const MAX_HASH_SIZE: usize = 1024;
+const MAX_MULTI_PV: usize = 256;
+
+// what the search thread and the main thread share while a search runs
+struct Control {
+    stop: Arc<AtomicBool>,
+    // set when the search returned, the stop flag is cleared then and mustn't be set again
+    finished: Mutex<bool>,
+    // an infinite or pondering search only sends its best move after stop or ponderhit
+    released: AtomicBool,
+}
+
+impl Control {
+    fn stop_search(&self) {
+        let finished = self.finished.lock().unwrap();
+        if !*finished {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+struct RunningSearch {
+    thread: JoinHandle<Searcher>,
+    control: Arc<Control>,
+    pondering: bool,
+    infinite: bool,
+    // tells the timer of a pondering search about the ponderhit
+    ponderhit: Option<Sender<()>>,
+}
+
+struct Engine {
+    position: Position,
+    options: SearchOptions,
+    // None while it searches
+    searcher: Option<Searcher>,
+    search: Option<RunningSearch>,
+}
+
+fn main() {
+    let mut engine = Engine {
+        position: Position::new(),
+        options: SearchOptions::default(),
+        searcher: Some(Searcher::new()),
+        search: None,
+    };
+
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match UciCommand::parse(&line) {
+            Ok(UciCommand::Quit) => break,
+            Ok(command) => engine.run(command),
+            Err(error) => println!("info string {}", error),
+        }
+    }
+    engine.finish_search();
+}
+
+impl Engine {
+    fn run(&mut self, command: UciCommand) {
+        match command {
+            UciCommand::Uci => {
+                println!("id name chess-core {}", env!("CARGO_PKG_VERSION"));
+                println!("id author {}", env!("CARGO_PKG_AUTHORS").split('<').next().unwrap_or("").trim());
+                println!("option name Hash type spin default {} min 1 max {}", DEFAULT_HASH_SIZE, MAX_HASH_SIZE);
+                // deliberately limited to one thread, the transposition table belongs to a single searcher and isn't shared
+                // between threads, the option is there for guis that always send it
+                println!("option name Threads type spin default 1 min 1 max 1");
+                println!("option name MultiPV type spin default 1 min 1 max {}", MAX_MULTI_PV);
+                println!("option name Ponder type check default false");
+                println!("option name Clear Hash type button");
+                println!("uciok");
+            }
+            UciCommand::Debug(_) => {}
+            UciCommand::IsReady => println!("readyok"),
+            UciCommand::SetOption { name, value } => self.set_option(&name, value.as_deref()),
+            UciCommand::UciNewGame => {
+                self.finish_search();
+                self.position = Position::new();
+                if let Some(searcher) = &mut self.searcher {
+                    searcher.clear_hash();
+                }
+            }
+            UciCommand::Position(position) => {
+                self.finish_search();
+                self.position = position;
+            }
+            UciCommand::Go(params) => {
+                self.finish_search();
+                self.go(params);
+            }
+            UciCommand::Stop => self.finish_search(),
+            UciCommand::PonderHit => {
+                if let Some(search) = &mut self.search {
+                    if search.pondering {
+                        search.pondering = false;
+                        // the timer starts now, the search goes on with the time of the move
+                        if let Some(ponderhit) = &search.ponderhit {
+                            let _ = ponderhit.send(());
+                        }
+                        if !search.infinite {
+                            search.control.released.store(true, Ordering::Relaxed);
+                        }
+                    }
+                }
+            }
+            UciCommand::Quit => {}
+        }
+    }
+
+    fn set_option(&mut self, name: &str, value: Option<&str>) {
+        self.finish_search();
+        let number = value.and_then(|value| value.parse::<usize>().ok());
+        match name.to_ascii_lowercase().as_str() {
+            "hash" => self.options.hash_size = number.unwrap_or(DEFAULT_HASH_SIZE).clamp(1, MAX_HASH_SIZE),
+            "multipv" => self.options.multi_pv = number.unwrap_or(1).clamp(1, MAX_MULTI_PV),
+            "clear hash" => {
+                if let Some(searcher) = &mut self.searcher {
+                    searcher.clear_hash();
+                }
+            }
+            "threads" => {
+                if number != Some(1) {
+                    println!("info string the search runs on one thread, Threads stays 1");
+                }
+            }
+            // the gui tells whether it will send go ponder, nothing to set up for it
+            "ponder" => {}
+            _ => println!("info string unknown option '{}'", name),
+        }
+        if let Some(searcher) = &mut self.searcher {
+            searcher.set_options(self.options);
+        }
+    }
+
+    // starts the search on its own thread, it sends the info lines and the best move itself
+    fn go(&mut self, params: GoParams) {
+        let mut searcher = self.searcher.take().unwrap_or_default();
+        searcher.set_options(self.options);
+        let position = self.position.clone();
+
+        let time = params.time_for_move(position.side_to_move());
+        let limits = SearchLimits {
+            // a mate in n moves is found within 2n plies
+            depth: params.depth.or(params.mate.map(|moves| moves * 2)),
+            // a pondering search has no time until the ponderhit
+            time: if params.ponder || params.infinite { None } else { time },
+            nodes: params.nodes,
+            moves: params.searchmoves.iter().filter_map(|uci| position.parse_uci(uci).ok()).collect(),
+        };
+
+        let control = Arc::new(Control {
+            stop: searcher.stop_handle(),
+            finished: Mutex::new(false),
+            released: AtomicBool::new(!params.ponder && !params.infinite),
+        });
+        let ponderhit = if params.ponder && !params.infinite {
+            time.map(|time| {
+                let (sender, receiver) = mpsc::channel();
+                let timer_control = control.clone();
+                thread::spawn(move || ponder_timer(receiver, time, timer_control));
+                sender
+            })
+        } else {
+            None
+        };
+
+        let thread_control = control.clone();
+        let thread = thread::spawn(move || {
+            let result = searcher.search_with(&position, limits, print_info);
+            *thread_control.finished.lock().unwrap() = true;
+            // a stop that came in while the search was returning is for this search, not the next one
+            thread_control.stop.store(false, Ordering::Relaxed);
+
+            while !thread_control.released.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            print_best_move(&position, &result);
+            searcher
+        });
+
+        self.search = Some(RunningSearch { thread, control, pondering: params.ponder, infinite: params.infinite, ponderhit });
+    }
+
+    // stops the running search and waits until it sent its best move
+    fn finish_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.control.stop_search();
+            search.control.released.store(true, Ordering::Relaxed);
+            if let Ok(searcher) = search.thread.join() {
+                self.searcher = Some(searcher);
+            }
+        }
+    }
+}
+
+// waits for the ponderhit and stops the search when the time of the move is up
+fn ponder_timer(ponderhit: Receiver<()>, time: Duration, control: Arc<Control>) {
+    if ponderhit.recv().is_err() {
+        return;
+    }
+    // the sender is gone once the search was stopped
+    if let Err(RecvTimeoutError::Timeout) = ponderhit.recv_timeout(time) {
+        control.stop_search();
+    }
+}
+
+fn print_info(result: &SearchResult) {
+    let nps = result.nodes * 1000 / (result.elapsed.as_millis() as u64).max(1);
+    for (index, line) in result.lines.iter().enumerate() {
+        let info = EngineInfo {
+            depth: Some(result.depth),
+            seldepth: Some(result.seldepth.max(result.depth)),
+            multipv: Some(index as u32 + 1),
+            score: Some(line.mate_in().map_or(Score::Centipawns(line.score), Score::Mate)),
+            nodes: Some(result.nodes),
+            nps: Some(nps),
+            hashfull: Some(result.hashfull),
+            time: Some(result.elapsed),
+            pv: line.pv.iter().map(|mv| mv.to_string()).collect(),
+            ..Default::default()
+        };
+        println!("{}", info);
+    }
+}
+
+// the best move and the expected answer to ponder on
+fn print_best_move(position: &Position, result: &SearchResult) {
+    match result.best_move {
+        Some(best_move) => match result.pv.get(1).filter(|_| result.pv.first() == Some(&best_move)) {
+            Some(ponder) => println!("bestmove {} ponder {}", best_move, ponder),
+            None => println!("bestmove {}", best_move)
+        },
+        None => {
+            // checkmated or stalemated, there is nothing to search
+            let score = if position.is_check() { Score::Mate(0) } else { Score::Centipawns(0) };
+            println!("{}", EngineInfo { depth: Some(0), score: Some(score), ..Default::default() });
+            println!("bestmove (none)");
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{attacks::*, bitboard::*, moves::*, position::*, types::*, zobrist::*};
+
+// pieces on the squares, copied to try out moves without touching the position
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Board {
+    // indexed by color and piece type
+    pieces: [[Bitboard; 6]; 2],
+    colors: [Bitboard; 2],
+    occupied: Bitboard,
+    // the same pieces by square, so piece_at doesn't have to look through the bitboards
+    squares: [Option<Piece>; 64],
+    // zobrist hash of the pieces, kept up to date by set
+    hash: u64,
+}
+
+impl Board {
+    pub(crate) fn empty() -> Board {
+        Board {
+            pieces: [[Bitboard::EMPTY; 6]; 2],
+            colors: [Bitboard::EMPTY; 2],
+            occupied: Bitboard::EMPTY,
+            squares: [None; 64],
+            hash: 0,
+        }
+    }
+
+    pub(crate) fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.squares[square.index()]
+    }
+
+    pub(crate) fn set(&mut self, square: Square, piece: Option<Piece>) {
+        let bit = Bitboard::from_square(square);
+        if let Some(old) = self.squares[square.index()] {
+            self.pieces[old.color as usize][old.piece_type as usize] ^= bit;
+            self.colors[old.color as usize] ^= bit;
+            self.occupied ^= bit;
+            self.hash ^= ZOBRIST.piece(old, square);
+        }
+        if let Some(new) = piece {
+            self.pieces[new.color as usize][new.piece_type as usize] |= bit;
+            self.colors[new.color as usize] |= bit;
+            self.occupied |= bit;
+            self.hash ^= ZOBRIST.piece(new, square);
+        }
+        self.squares[square.index()] = piece;
+    }
+
+    pub(crate) fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    pub(crate) fn pieces(&self, color: PieceColor, piece_type: PieceType) -> Bitboard {
+        self.pieces[color as usize][piece_type as usize]
+    }
+
+    pub(crate) fn color(&self, color: PieceColor) -> Bitboard {
+        self.colors[color as usize]
+    }
+
+    pub(crate) fn occupied(&self) -> Bitboard {
+        self.occupied
+    }
+
+    pub(crate) fn king_square(&self, color: PieceColor) -> Option<Square> {
+        self.pieces(color, PieceType::King).first()
+    }
+
+    // returns true if there is no piece between the two squares, they have to be on a line
+    pub(crate) fn is_path_empty(&self, from: Square, to: Square) -> bool {
+        (between(from, to) & self.occupied).is_empty()
+    }
+
+    // squares the piece on from could take on, no matter what is on them
+    pub(crate) fn piece_attacks(&self, from: Square, piece: Piece) -> Bitboard {
+        match piece.piece_type {
+            PieceType::King => king_attacks(from),
+            PieceType::Queen => queen_attacks(from, self.occupied),
+            PieceType::Bishop => bishop_attacks(from, self.occupied),
+            PieceType::Knight => knight_attacks(from),
+            PieceType::Rook => rook_attacks(from, self.occupied),
+            // pawns only take diagonally forward
+            PieceType::Pawn => pawn_attacks(piece.color, from),
+        }
+    }
+
+    // returns true if the piece on from could take on to, no matter what is on it
+    pub(crate) fn attacks(&self, from: Square, piece: Piece, to: Square) -> bool {
+        self.piece_attacks(from, piece).contains(to)
+    }
+
+    // returns true if any piece of the given color could take on the square
+    pub(crate) fn is_attacked(&self, square: Square, by: PieceColor) -> bool {
+        // look from the square outwards for pieces that could reach it
+        let queens = self.pieces(by, PieceType::Queen);
+        let attackers = (knight_attacks(square) & self.pieces(by, PieceType::Knight))
+            | (king_attacks(square) & self.pieces(by, PieceType::King))
+            // a pawn attacks the square from where a pawn of the other color on the square would take
+            | (pawn_attacks(by.opposite(), square) & self.pieces(by, PieceType::Pawn))
+            | (bishop_attacks(square, self.occupied) & (self.pieces(by, PieceType::Bishop) | queens))
+            | (rook_attacks(square, self.occupied) & (self.pieces(by, PieceType::Rook) | queens));
+        !attackers.is_empty()
+    }
+
+    // moves the pieces, including the rook when castling and the pawn taken en passant
+    // returns the piece that was taken
+    pub(crate) fn apply(&mut self, mv: Move, en_passant: Option<Square>) -> Option<Piece> {
+        let mut piece = self.piece_at(mv.from).expect("no piece to move");
+        let mut captured = self.piece_at(mv.to);
+
+        // en passant takes the pawn next to the moving one, not on the target square
+        if piece.piece_type == PieceType::Pawn && mv.from.file() != mv.to.file() && Some(mv.to) == en_passant {
+            let pawn_square = Square::new(mv.from.rank(), mv.to.file());
+            captured = self.piece_at(pawn_square);
+            self.set(pawn_square, None);
+        }
+
+        if let Some(side) = castling_side(piece, mv) {
+            let rook_from = Square::new(mv.from.rank(), side.rook_file());
+            let rook_to = Square::new(mv.from.rank(), side.rook_to_file());
+            self.set(rook_to, self.piece_at(rook_from));
+            self.set(rook_from, None);
+        }
+
+        if let Some(promotion) = mv.promotion {
+            piece.piece_type = promotion;
+        }
+
+        self.set(mv.from, None);
+        self.set(mv.to, Some(piece));
+        captured
+    }
+}
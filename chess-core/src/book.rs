@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use crate::{moves::*, notation::*, position::*};
+
+// main lines of common openings in uci notation, every position on them is in the built-in book
+const LINES: [&str; 20] = [
+    // ruy lopez
+    "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6 e1g1 f8e7 f1e1 b7b5 a4b3 d7d6 c2c3 e8g8",
+    "e2e4 e7e5 g1f3 b8c6 f1b5 g8f6 e1g1 f6e4 d2d4 e4d6 b5c6 d7c6 d4e5 d6f5",
+    // italian game
+    "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5 c2c3 g8f6 d2d3 d7d6 e1g1 e8g8",
+    "e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 d2d3 f8e7 e1g1 e8g8",
+    // scotch game
+    "e2e4 e7e5 g1f3 b8c6 d2d4 e5d4 f3d4 g8f6 d4c6 b7c6 e4e5",
+    // petrov defense
+    "e2e4 e7e5 g1f3 g8f6 f3e5 d7d6 e5f3 f6e4 d2d4 d6d5",
+    // sicilian defense
+    "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 a7a6 c1e3 e7e5",
+    "e2e4 c7c5 g1f3 b8c6 d2d4 c5d4 f3d4 g8f6 b1c3 e7e5 d4b5 d7d6",
+    "e2e4 c7c5 g1f3 e7e6 d2d4 c5d4 f3d4 b8c6 b1c3 d8c7",
+    // french defense
+    "e2e4 e7e6 d2d4 d7d5 b1c3 g8f6 c1g5 f8e7 e4e5 f6d7",
+    "e2e4 e7e6 d2d4 d7d5 b1d2 c7c5 g1f3 g8f6",
+    // caro-kann defense
+    "e2e4 c7c6 d2d4 d7d5 b1c3 d5e4 c3e4 c8f5 e4g3 f5g6",
+    // queen's gambit declined
+    "d2d4 d7d5 c2c4 e7e6 b1c3 g8f6 c1g5 f8e7 e2e3 e8g8 g1f3",
+    // slav defense
+    "d2d4 d7d5 c2c4 c7c6 g1f3 g8f6 b1c3 d5c4 a2a4 c8f5",
+    // queen's gambit accepted
+    "d2d4 d7d5 c2c4 d5c4 g1f3 g8f6 e2e3 e7e6 f1c4 c7c5 e1g1 a7a6",
+    // nimzo-indian defense
+    "d2d4 g8f6 c2c4 e7e6 b1c3 f8b4 e2e3 e8g8 f1d3 d7d5 g1f3 c7c5",
+    // king's indian defense
+    "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7 e2e4 d7d6 g1f3 e8g8 f1e2 e7e5 e1g1 b8c6",
+    // english opening
+    "c2c4 e7e5 b1c3 g8f6 g1f3 b8c6 g2g3 d7d5 c4d5 f6d5",
+    "c2c4 g8f6 b1c3 e7e6 g1f3 d7d5 d2d4",
+    // reti opening
+    "g1f3 d7d5 g2g3 g8f6 f1g2 e7e6 e1g1 f8e7 d2d3 e8g8",
+];
+
+// moves to play in known positions, found by the zobrist hash of the position
+#[derive(Default)]
+pub struct OpeningBook {
+    moves: HashMap<u64, Vec<Move>>,
+}
+
+impl OpeningBook {
+    pub fn new() -> OpeningBook {
+        OpeningBook::default()
+    }
+
+    // the lines of the common openings from the standard start position
+    pub fn builtin() -> OpeningBook {
+        let mut book = OpeningBook::new();
+        for line in LINES.iter() {
+            book.add_line(&Position::new(), line).expect("invalid move in the built-in book");
+        }
+        book
+    }
+
+    // adds every move of the line, given in uci notation and separated by whitespace, played from the start
+    pub fn add_line(&mut self, start: &Position, line: &str) -> Result<(), NotationError> {
+        let mut position = start.clone();
+        for uci in line.split_whitespace() {
+            let mv = position.parse_uci(uci)?;
+            let moves = self.moves.entry(position.hash()).or_default();
+            if !moves.contains(&mv) {
+                moves.push(mv);
+            }
+            position.make_move(mv);
+        }
+        Ok(())
+    }
+
+    // the book moves of the position, empty if the book doesn't know it
+    pub fn moves(&self, position: &Position) -> Vec<Move> {
+        self.moves.get(&position.hash()).map_or_else(Vec::new, |moves| {
+            // two positions can share a hash, a move that isn't legal here belongs to the other one
+            moves.iter().copied().filter(|mv| position.is_legal(*mv)).collect()
+        })
+    }
+
+    // one of the book moves, random picks the move so the computer doesn't play the same opening every game
+    pub fn pick(&self, position: &Position, random: u64) -> Option<Move> {
+        let moves = self.moves(position);
+        if moves.is_empty() {
+            None
+        } else {
+            Some(moves[(random % moves.len() as u64) as usize])
+        }
+    }
+}
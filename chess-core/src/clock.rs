@@ -0,0 +1,315 @@
+use std::{error::Error, fmt};
+use std::time::Duration;
+
+use crate::{types::*, uci::*};
+
+// how a stage gives extra time for every move
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum TimingMethod {
+    // fischer increment, added after every move
+    #[default]
+    Increment,
+    // the time used for the move is given back after it, up to the delay
+    Bronstein,
+    // simple or us delay, the clock only counts down after the delay
+    SimpleDelay,
+}
+
+// a period of the game, like 40 moves in 90 minutes
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TimeStage {
+    // moves to play in the stage, None for the rest of the game
+    pub moves: Option<u32>,
+    pub time: Duration,
+    // the increment or the delay of every move in the stage
+    pub increment: Duration,
+    pub method: TimingMethod,
+}
+
+// the stages of a game, the last one repeats if it has a move count
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct TimeControl {
+    pub stages: Vec<TimeStage>,
+}
+
+// what is wrong with a written time control
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TimeControlError {
+    Empty,
+    // not written like "40/90+30", "5+3", "5d2" or "5b2"
+    InvalidStage(String),
+    // only the last stage can be for the rest of the game
+    StageAfterRestOfGame(String),
+}
+
+impl fmt::Display for TimeControlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeControlError::Empty => write!(f, "a time control needs at least one stage"),
+            TimeControlError::InvalidStage(stage) => write!(f, "'{}' is not a stage like 40/90+30, 5+3, 5d2 or 5b2 with minutes and seconds", stage),
+            TimeControlError::StageAfterRestOfGame(stage) => write!(f, "stage '{}' comes after a stage for the rest of the game", stage),
+        }
+    }
+}
+
+impl Error for TimeControlError {}
+
+impl TimeControl {
+    pub fn sudden_death(time: Duration) -> TimeControl {
+        TimeControl::fischer(time, Duration::from_secs(0))
+    }
+
+    pub fn fischer(time: Duration, increment: Duration) -> TimeControl {
+        TimeControl::single(time, increment, TimingMethod::Increment)
+    }
+
+    pub fn bronstein(time: Duration, delay: Duration) -> TimeControl {
+        TimeControl::single(time, delay, TimingMethod::Bronstein)
+    }
+
+    pub fn simple_delay(time: Duration, delay: Duration) -> TimeControl {
+        TimeControl::single(time, delay, TimingMethod::SimpleDelay)
+    }
+
+    fn single(time: Duration, increment: Duration, method: TimingMethod) -> TimeControl {
+        TimeControl { stages: vec![TimeStage { moves: None, time, increment, method }] }
+    }
+
+    // reads stages separated by ':' with minutes and seconds, like "40/90+30:30+30" for 40 moves in 90 minutes and
+    // then 30 minutes for the rest of the game with 30 seconds increment, 'd' instead of '+' is a simple delay and
+    // 'b' a bronstein delay
+    pub fn parse(text: &str) -> Result<TimeControl, TimeControlError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(TimeControlError::Empty);
+        }
+
+        let mut stages: Vec<TimeStage> = Vec::new();
+        for part in text.split(':') {
+            let part = part.trim();
+            if stages.last().is_some_and(|stage| stage.moves.is_none()) {
+                return Err(TimeControlError::StageAfterRestOfGame(part.to_string()));
+            }
+            stages.push(parse_stage(part).ok_or_else(|| TimeControlError::InvalidStage(part.to_string()))?);
+        }
+        Ok(TimeControl { stages })
+    }
+
+    // the stage of the given number, counted from 0, the last stage repeats
+    pub fn stage(&self, number: usize) -> &TimeStage {
+        &self.stages[number.min(self.stages.len() - 1)]
+    }
+
+    // number of the stage a player plays in after making the given number of moves
+    pub fn stage_number(&self, moves: u32) -> usize {
+        let mut end = 0;
+        let mut number = 0;
+        loop {
+            match self.stage(number).moves {
+                Some(count) if count > 0 => {
+                    end += count;
+                    if moves < end {
+                        return number;
+                    }
+                    number += 1;
+                }
+                _ => return number
+            }
+        }
+    }
+
+    // moves left until the next stage after making the given number of moves, None in the last stage
+    pub fn moves_to_go(&self, moves: u32) -> Option<u32> {
+        let number = self.stage_number(moves);
+        let end: u32 = (0..=number).filter_map(|number| self.stage(number).moves).sum();
+        self.stage(number).moves.map(|_| end - moves)
+    }
+
+    // the TimeControl tag of a pgn, in seconds like "40/5400+30:1800+30", delays are written as increments
+    pub fn to_pgn(&self) -> String {
+        let stages: Vec<String> = self.stages.iter().map(|stage| {
+            let mut text = match stage.moves {
+                Some(moves) => format!("{}/{}", moves, stage.time.as_secs()),
+                None => stage.time.as_secs().to_string()
+            };
+            if stage.increment > Duration::from_secs(0) {
+                text += &format!("+{}", stage.increment.as_secs());
+            }
+            text
+        }).collect();
+        stages.join(":")
+    }
+}
+
+// "40/90+30", "5+3", "90", "5d2" or "0.5b1"
+fn parse_stage(text: &str) -> Option<TimeStage> {
+    let (moves, rest) = match text.split_once('/') {
+        Some((moves, rest)) => (Some(moves.trim().parse::<u32>().ok().filter(|moves| *moves > 0)?), rest),
+        None => (None, text)
+    };
+    let (minutes, increment, method) = match rest.find(['+', 'd', 'b']) {
+        Some(index) => {
+            let method = match &rest[index..index + 1] {
+                "+" => TimingMethod::Increment,
+                "d" => TimingMethod::SimpleDelay,
+                _ => TimingMethod::Bronstein
+            };
+            (&rest[..index], seconds(&rest[index + 1..])?, method)
+        }
+        None => (rest, Duration::from_secs(0), TimingMethod::Increment)
+    };
+    let time = seconds(minutes)?.checked_mul(60)?;
+    if time == Duration::from_secs(0) {
+        return None;
+    }
+    Some(TimeStage { moves, time, increment, method })
+}
+
+fn seconds(text: &str) -> Option<Duration> {
+    text.trim().parse::<f64>().ok().filter(|seconds| seconds.is_finite() && *seconds >= 0.).map(Duration::from_secs_f64)
+}
+
+// the written form read by parse, like "40/90+30:30+30"
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, stage) in self.stages.iter().enumerate() {
+            if index > 0 {
+                write!(f, ":")?;
+            }
+            if let Some(moves) = stage.moves {
+                write!(f, "{}/", moves)?;
+            }
+            write!(f, "{}", stage.time.as_secs_f64() / 60.)?;
+            let separator = match stage.method {
+                TimingMethod::Increment => '+',
+                TimingMethod::SimpleDelay => 'd',
+                TimingMethod::Bronstein => 'b',
+            };
+            if stage.increment > Duration::from_secs(0) || stage.method != TimingMethod::Increment {
+                write!(f, "{}{}", separator, stage.increment.as_secs_f64())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// the times of both players under a time control, it is told how much time passes and when a move is made
+#[derive(Clone, Debug)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: [Duration; 2],
+    // moves each player made, they decide the stage
+    moves: [u32; 2],
+    turn: PieceColor,
+    // time the player to move spent on the current move
+    used: Duration,
+    flagged: Option<PieceColor>,
+}
+
+impl Clock {
+    // both players start with the time of the first stage, the clock of the given color runs first
+    pub fn new(control: TimeControl, turn: PieceColor) -> Clock {
+        let time = control.stage(0).time;
+        Clock {
+            control,
+            remaining: [time; 2],
+            moves: [0; 2],
+            turn,
+            used: Duration::from_secs(0),
+            flagged: None,
+        }
+    }
+
+    pub fn control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    pub fn remaining(&self, color: PieceColor) -> Duration {
+        self.remaining[color as usize]
+    }
+
+    // color of the clock that runs
+    pub fn turn(&self) -> PieceColor {
+        self.turn
+    }
+
+    // the player whose time ran out
+    pub fn flagged(&self) -> Option<PieceColor> {
+        self.flagged
+    }
+
+    pub fn moves_to_go(&self, color: PieceColor) -> Option<u32> {
+        self.control.moves_to_go(self.moves[color as usize])
+    }
+
+    // the stage the player plays the next move in
+    pub fn stage(&self, color: PieceColor) -> &TimeStage {
+        self.control.stage(self.control.stage_number(self.moves[color as usize]))
+    }
+
+    // how much of a simple delay is left before the running clock counts down
+    pub fn delay_left(&self) -> Duration {
+        let stage = self.stage(self.turn);
+        match stage.method {
+            TimingMethod::SimpleDelay => stage.increment.saturating_sub(self.used),
+            _ => Duration::from_secs(0)
+        }
+    }
+
+    // lets time pass on the running clock, it stops when the time runs out
+    pub fn tick(&mut self, elapsed: Duration) {
+        if self.flagged.is_some() {
+            return;
+        }
+
+        let delay_left = self.delay_left();
+        self.used += elapsed;
+        let remaining = &mut self.remaining[self.turn as usize];
+        *remaining = remaining.saturating_sub(elapsed.saturating_sub(delay_left));
+        if *remaining == Duration::from_secs(0) {
+            self.flagged = Some(self.turn);
+        }
+    }
+
+    // the player to move made their move, they get the increment and the time of the next stage and the other clock runs
+    pub fn press(&mut self) {
+        if self.flagged.is_some() {
+            return;
+        }
+
+        let color = self.turn as usize;
+        let stage = *self.stage(self.turn);
+        match stage.method {
+            TimingMethod::Increment => self.remaining[color] += stage.increment,
+            TimingMethod::Bronstein => self.remaining[color] += stage.increment.min(self.used),
+            TimingMethod::SimpleDelay => {}
+        }
+
+        let number = self.control.stage_number(self.moves[color]);
+        self.moves[color] += 1;
+        let next = self.control.stage_number(self.moves[color]);
+        if next != number {
+            self.remaining[color] += self.control.stage(next).time;
+        }
+
+        self.used = Duration::from_secs(0);
+        self.turn = self.turn.opposite();
+    }
+
+    // the clock times for a uci engine playing the side to move
+    pub fn go_params(&self) -> GoParams {
+        let increment = |color: PieceColor| {
+            let stage = self.stage(color);
+            // a delay isn't an increment, but the engine can spend it the same way
+            Some(stage.increment).filter(|increment| *increment > Duration::from_secs(0))
+        };
+        GoParams {
+            wtime: Some(self.remaining(PieceColor::White)),
+            btime: Some(self.remaining(PieceColor::Black)),
+            winc: increment(PieceColor::White),
+            binc: increment(PieceColor::Black),
+            movestogo: self.moves_to_go(self.turn),
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use std::{error::Error, fmt};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{moves::*, position::*, types::*, uci::*};
+
+// how long an engine gets to answer `uci` and `isready`
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+// how long an engine gets past its search time to send the best move
+pub const MOVE_GRACE: Duration = Duration::from_secs(2);
+
+// what went wrong talking to an engine
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum UciError {
+    // the program couldn't be started
+    Spawn(String),
+    // writing to the engine failed
+    Io(String),
+    // the engine didn't send the named answer in time
+    Timeout(&'static str),
+    // the engine closed its output, most likely it exited
+    Crashed,
+    // the engine sent something that doesn't follow the protocol
+    Protocol(String),
+    // the engine's best move isn't legal in the position
+    IllegalMove(String),
+    // the engine found no move, the game is already over
+    NoMove,
+}
+
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UciError::Spawn(error) => write!(f, "couldn't start the engine: {}", error),
+            UciError::Io(error) => write!(f, "couldn't write to the engine: {}", error),
+            UciError::Timeout(answer) => write!(f, "the engine didn't send '{}' in time", answer),
+            UciError::Crashed => write!(f, "the engine stopped running"),
+            UciError::Protocol(line) => write!(f, "the engine sent '{}' which doesn't follow the uci protocol", line),
+            UciError::IllegalMove(mv) => write!(f, "the engine played {} which is not a legal move", mv),
+            UciError::NoMove => write!(f, "the engine found no move to play"),
+        }
+    }
+}
+
+impl Error for UciError {}
+
+// an option the engine offers, from its `option` lines
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EngineOption {
+    pub name: String,
+    // check, spin, combo, button or string
+    pub kind: String,
+    pub default: Option<String>,
+}
+
+// a chess engine program talking the universal chess interface over its standard input and output
+pub struct UciEngine {
+    path: PathBuf,
+    args: Vec<String>,
+    child: Child,
+    stdin: ChildStdin,
+    // lines from the engine, read on their own thread so waiting for them can time out,
+    // the mutex only makes the engine shareable between threads and is never contended
+    lines: Mutex<Receiver<String>>,
+    name: String,
+    author: Option<String>,
+    options: Vec<EngineOption>,
+    // options set so far, sent again after a restart
+    set_options: Vec<(String, String)>,
+    stop: Arc<AtomicBool>,
+}
+
+impl UciEngine {
+    // starts the program and waits until it is ready
+    pub fn start(path: impl AsRef<Path>, args: &[String]) -> Result<UciEngine, UciError> {
+        let path = path.as_ref().to_path_buf();
+        let mut child = Command::new(&path)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| UciError::Spawn(error.to_string()))?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, lines) = mpsc::channel();
+        // the channel disconnects when the engine closes its output
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => if sender.send(line).is_err() { break },
+                    Err(_) => break
+                }
+            }
+        });
+
+        let fallback_name = path.file_stem().map_or_else(|| path.display().to_string(), |stem| stem.to_string_lossy().into_owned());
+        let mut engine = UciEngine {
+            path,
+            args: args.to_vec(),
+            child,
+            stdin,
+            lines: Mutex::new(lines),
+            name: fallback_name,
+            author: None,
+            options: Vec::new(),
+            set_options: Vec::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+        };
+        engine.handshake()?;
+        Ok(engine)
+    }
+
+    fn handshake(&mut self) -> Result<(), UciError> {
+        self.send("uci")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        loop {
+            let line = self.receive(Some(deadline), "uciok")?;
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("uciok") => break,
+                Some("id") => match words.next() {
+                    Some("name") => self.name = words.collect::<Vec<_>>().join(" "),
+                    Some("author") => self.author = Some(words.collect::<Vec<_>>().join(" ")),
+                    _ => {}
+                },
+                Some("option") => {
+                    if let Some(option) = parse_option(&line) {
+                        self.options.push(option);
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.is_ready()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn options(&self) -> &[EngineOption] {
+        &self.options
+    }
+
+    pub fn has_option(&self, name: &str) -> bool {
+        self.options.iter().any(|option| option.name.eq_ignore_ascii_case(name))
+    }
+
+    // setting the flag from another thread stops the search, the engine still sends its best move,
+    // the flag is cleared when the search ends so a stop set before the search starts stops it right away
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    // false once the program exited
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), UciError> {
+        self.send(&format!("setoption name {} value {}", name, value))?;
+        self.set_options.retain(|(set_name, _)| set_name != name);
+        self.set_options.push((name.to_string(), value.to_string()));
+        self.is_ready()
+    }
+
+    // the engine forgets what it knows about the last game
+    pub fn new_game(&mut self) -> Result<(), UciError> {
+        self.send("ucinewgame")?;
+        self.is_ready()
+    }
+
+    // waits until the engine answers `isready`
+    pub fn is_ready(&mut self) -> Result<(), UciError> {
+        self.send("isready")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        while self.receive(Some(deadline), "readyok")? != "readyok" {}
+        Ok(())
+    }
+
+    // starts the program again after it crashed or hung, with the options set so far
+    pub fn restart(&mut self) -> Result<(), UciError> {
+        let mut engine = UciEngine::start(&self.path, &self.args)?;
+        for (name, value) in self.set_options.iter() {
+            engine.set_option(name, value)?;
+        }
+        // the old process is shut down when it gets dropped
+        std::mem::swap(self, &mut engine);
+        Ok(())
+    }
+
+    // searches the position reached by playing the moves from the start and returns the engine's best move,
+    // calls on_info for every info line the engine sends while it searches
+    pub fn go(&mut self, start: &Position, moves: &[Move], params: &GoParams, on_info: impl FnMut(&EngineInfo)) -> Result<Move, UciError> {
+        let result = self.search(start, moves, params, on_info);
+        self.stop.store(false, Ordering::Relaxed);
+        result
+    }
+
+    fn search(&mut self, start: &Position, moves: &[Move], params: &GoParams, mut on_info: impl FnMut(&EngineInfo)) -> Result<Move, UciError> {
+        let mut position = start.clone();
+        for mv in moves {
+            position.make_move(*mv);
+        }
+
+        self.send(&position_command(start, moves))?;
+        self.send(&params.to_command())?;
+
+        // the engine is told to stop once its time is up and must answer soon after
+        let mut deadline = search_time(params, position.side_to_move()).map(|time| Instant::now() + time);
+        let mut stopped = false;
+        loop {
+            if !stopped && (self.stop.load(Ordering::Relaxed) || deadline.is_some_and(|deadline| Instant::now() >= deadline)) {
+                self.send("stop")?;
+                stopped = true;
+                deadline = Some(Instant::now() + MOVE_GRACE);
+            }
+
+            // waits in small steps to see the stop flag
+            let step = Instant::now() + Duration::from_millis(20);
+            let line = match self.receive(Some(deadline.map_or(step, |deadline| deadline.min(step))), "bestmove") {
+                Ok(line) => line,
+                Err(UciError::Timeout(_)) if !stopped || Instant::now() < deadline.expect("a stopped search has a deadline") => continue,
+                Err(error) => return Err(error)
+            };
+
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("info") => on_info(&EngineInfo::parse(&line["info".len()..])),
+                Some("bestmove") => {
+                    return match words.next() {
+                        Some("(none)") | Some("0000") => Err(UciError::NoMove),
+                        Some(uci) => position.parse_uci(uci).map_err(|_| UciError::IllegalMove(uci.to_string())),
+                        None => Err(UciError::Protocol(line))
+                    };
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), UciError> {
+        writeln!(self.stdin, "{}", command).and_then(|_| self.stdin.flush()).map_err(|error| {
+            if error.kind() == std::io::ErrorKind::BrokenPipe {
+                UciError::Crashed
+            } else {
+                UciError::Io(error.to_string())
+            }
+        })
+    }
+
+    // the next line from the engine, waiting names the answer the caller waits for
+    fn receive(&mut self, deadline: Option<Instant>, waiting: &'static str) -> Result<String, UciError> {
+        let lines = self.lines.get_mut().unwrap_or_else(|error| error.into_inner());
+        let line = match deadline {
+            Some(deadline) => lines.recv_timeout(deadline.saturating_duration_since(Instant::now())).map_err(|error| match error {
+                RecvTimeoutError::Timeout => UciError::Timeout(waiting),
+                RecvTimeoutError::Disconnected => UciError::Crashed
+            })?,
+            None => lines.recv().map_err(|_| UciError::Crashed)?
+        };
+        Ok(line.trim().to_string())
+    }
+}
+
+impl Drop for UciEngine {
+    // asks the engine to quit and kills it if it doesn't
+    fn drop(&mut self) {
+        let _ = writeln!(self.stdin, "quit").and_then(|_| self.stdin.flush());
+        let deadline = Instant::now() + Duration::from_millis(200);
+        while Instant::now() < deadline {
+            if !matches!(self.child.try_wait(), Ok(None)) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// how long the engine may search, None if it searches to a depth or node count or until stopped
+fn search_time(params: &GoParams, side_to_move: PieceColor) -> Option<Duration> {
+    if params.infinite || params.ponder {
+        return None;
+    }
+    let clock = match side_to_move {
+        PieceColor::White => params.wtime,
+        PieceColor::Black => params.btime
+    };
+    params.movetime.or(clock)
+}
+
+// "option name Hash type spin default 16 min 1 max 33554432"
+fn parse_option(line: &str) -> Option<EngineOption> {
+    let name_start = line.find(" name ")? + " name ".len();
+    let rest = &line[name_start..];
+    let type_start = rest.find(" type ")?;
+    let name = rest[..type_start].trim().to_string();
+    let mut words = rest[type_start + " type ".len()..].split_whitespace();
+    let kind = words.next()?.to_string();
+    let mut default = None;
+    while let Some(word) = words.next() {
+        if word == "default" {
+            default = words.next().map(str::to_string);
+        }
+    }
+    Some(EngineOption { name, kind, default })
+}
@@ -0,0 +1,225 @@
+use crate::{attacks::*, bitboard::*, position::*, types::*};
+
+// values of the pieces in centipawns, indexed by the piece type, the king can't be taken
+const PIECE_VALUES: [i32; 6] = [0, 900, 330, 320, 500, 100];
+
+// how much each piece type counts towards the middlegame, the sum at the start is 24
+const PHASE_WEIGHTS: [i32; 6] = [0, 4, 1, 1, 2, 0];
+const MAX_PHASE: i32 = 24;
+
+// bonus per square from the view of white, a8 is the first entry and h1 the last
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+// the king hides behind its pawns while there are pieces to attack it
+const KING_MIDDLEGAME_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+// and walks to the center once they are gone
+const KING_ENDGAME_TABLE: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+// bonus for a passed pawn by how far it got, from the view of its own color
+const PASSED_PAWN_BONUS: [i32; 8] = [0, 5, 10, 20, 35, 60, 100, 0];
+
+// what the computer cares about besides the material
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Style {
+    #[default]
+    Balanced,
+    // goes for the opponent's king
+    Aggressive,
+    // places its pieces well and keeps a healthy pawn structure
+    Positional,
+}
+
+impl Style {
+    pub const ALL: [Style; 3] = [Style::Balanced, Style::Aggressive, Style::Positional];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Style::Balanced => "balanced",
+            Style::Aggressive => "aggressive",
+            Style::Positional => "positional",
+        }
+    }
+}
+
+pub fn piece_value(piece_type: PieceType) -> i32 {
+    PIECE_VALUES[piece_type as usize]
+}
+
+// index into the tables, which are written from the view of white with rank 8 on top
+fn table_index(square: Square, color: PieceColor) -> usize {
+    match color {
+        PieceColor::White => (7 - square.rank() as usize) * 8 + square.file() as usize,
+        PieceColor::Black => square.index(),
+    }
+}
+
+impl Position {
+    // material and piece-square tables in centipawns from the view of the player to move
+    pub fn evaluate(&self) -> i32 {
+        self.material_and_placement(1, 1)
+    }
+
+    // the evaluation with the terms of the style added
+    pub fn evaluate_style(&self, style: Style) -> i32 {
+        let us = self.side_to_move();
+        let them = us.opposite();
+        match style {
+            Style::Balanced => self.evaluate(),
+            Style::Aggressive => self.evaluate() + self.king_attack(us) - self.king_attack(them),
+            Style::Positional => self.material_and_placement(3, 2) + self.pawn_structure(us) - self.pawn_structure(them)
+                + self.bishop_pair(us) - self.bishop_pair(them),
+        }
+    }
+
+    // the piece-square tables count numerator / denominator times
+    fn material_and_placement(&self, numerator: i32, denominator: i32) -> i32 {
+        let phase = self.pieces().map(|(_, piece)| PHASE_WEIGHTS[piece.piece_type as usize]).sum::<i32>().min(MAX_PHASE);
+
+        let mut score = 0;
+        for (square, piece) in self.pieces() {
+            let index = table_index(square, piece.color);
+            let value = PIECE_VALUES[piece.piece_type as usize] + numerator * match piece.piece_type {
+                PieceType::Pawn => PAWN_TABLE[index],
+                PieceType::Knight => KNIGHT_TABLE[index],
+                PieceType::Bishop => BISHOP_TABLE[index],
+                PieceType::Rook => ROOK_TABLE[index],
+                PieceType::Queen => QUEEN_TABLE[index],
+                // blend between the tables as the pieces come off the board
+                PieceType::King => (KING_MIDDLEGAME_TABLE[index] * phase + KING_ENDGAME_TABLE[index] * (MAX_PHASE - phase)) / MAX_PHASE,
+            } / denominator;
+
+            if piece.color == self.side_to_move() {
+                score += value;
+            } else {
+                score -= value;
+            }
+        }
+        score
+    }
+
+    // pieces of the color that attack the squares around the opponent's king
+    fn king_attack(&self, color: PieceColor) -> i32 {
+        let king_square = match self.board.king_square(color.opposite()) {
+            Some(king_square) => king_square,
+            None => return 0
+        };
+        let king_zone = king_attacks(king_square) | Bitboard::from_square(king_square);
+
+        let mut score = 0;
+        for square in self.board.color(color) {
+            let piece = self.piece_at(square).expect("bitboards out of sync");
+            if piece.piece_type != PieceType::King && piece.piece_type != PieceType::Pawn {
+                score += 10 * (self.board.piece_attacks(square, piece) & king_zone).count() as i32;
+            }
+        }
+        score
+    }
+
+    // doubled and isolated pawns cost, passed pawns are worth more the further they got
+    fn pawn_structure(&self, color: PieceColor) -> i32 {
+        let pawns = self.board.pieces(color, PieceType::Pawn);
+        let opponent_pawns = self.board.pieces(color.opposite(), PieceType::Pawn);
+        let neighbour_files = |file: u8| (file.saturating_sub(1)..=(file + 1).min(7)).fold(Bitboard::EMPTY, |files, file| files | Bitboard::file(file));
+
+        let mut score = 0;
+        for file in 0..8 {
+            let on_file = (pawns & Bitboard::file(file)).count() as i32;
+            if on_file > 1 {
+                score -= 15 * (on_file - 1);
+            }
+        }
+
+        for square in pawns {
+            let files = neighbour_files(square.file());
+            if (pawns & files & !Bitboard::file(square.file())).is_empty() {
+                score -= 12;
+            }
+
+            // no pawn of the opponent in front of it on its own or the neighbour files
+            let relative_rank = if color == PieceColor::White { square.rank() } else { 7 - square.rank() };
+            let in_front = opponent_pawns & files & !(0..=relative_rank).fold(Bitboard::EMPTY, |ranks, rank| {
+                ranks | Bitboard::rank(if color == PieceColor::White { rank } else { 7 - rank })
+            });
+            if in_front.is_empty() {
+                score += PASSED_PAWN_BONUS[relative_rank as usize];
+            }
+        }
+        score
+    }
+
+    fn bishop_pair(&self, color: PieceColor) -> i32 {
+        if self.board.pieces(color, PieceType::Bishop).count() >= 2 { 30 } else { 0 }
+    }
+}
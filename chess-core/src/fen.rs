@@ -0,0 +1,267 @@
+use std::{error::Error, fmt};
+
+use crate::{board::*, notation::*, position::*, types::*};
+
+// what is wrong with a fen string
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum FenError {
+    // a fen needs the piece placement, side to move, castling rights and en passant square,
+    // the two clocks can be left out
+    FieldCount(usize),
+    RankCount(usize),
+    // rank counted from 1 like in the fen, and the number of squares it describes
+    RankLength { rank: u8, squares: usize },
+    InvalidPiece(char),
+    InvalidSideToMove(String),
+    InvalidCastlingRights(String),
+    InvalidEnPassant(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    // every side needs exactly one king
+    KingCount { color: PieceColor, count: usize },
+    PawnOnBackRank(Square),
+    // the player who just moved can't have left their king in check
+    OpponentInCheck,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::FieldCount(count) => write!(f, "expected 4 to 6 fields separated by spaces, found {}", count),
+            FenError::RankCount(count) => write!(f, "expected 8 ranks separated by '/', found {}", count),
+            FenError::RankLength { rank, squares } => write!(f, "rank {} describes {} squares instead of 8", rank, squares),
+            FenError::InvalidPiece(piece) => write!(f, "'{}' is not a piece, expected one of KQBNRPkqbnrp or a digit", piece),
+            FenError::InvalidSideToMove(side) => write!(f, "side to move '{}' must be 'w' or 'b'", side),
+            FenError::InvalidCastlingRights(rights) => write!(f, "castling rights '{}' must be '-' or a combination of KQkq that matches the kings and rooks", rights),
+            FenError::InvalidEnPassant(square) => write!(f, "en passant square '{}' must be '-' or a square on rank 3 or 6 behind a pawn that just moved", square),
+            FenError::InvalidHalfmoveClock(clock) => write!(f, "halfmove clock '{}' must be a number", clock),
+            FenError::InvalidFullmoveNumber(number) => write!(f, "fullmove number '{}' must be a number above 0", number),
+            FenError::KingCount { color, count } => write!(f, "{:?} has {} kings instead of 1", color, count),
+            FenError::PawnOnBackRank(square) => write!(f, "pawn on {} can't stand on the first or last rank", square),
+            FenError::OpponentInCheck => write!(f, "the king of the player who just moved is in check"),
+        }
+    }
+}
+
+impl Error for FenError {}
+
+impl Position {
+    // loads a position in forsyth-edwards notation, like
+    // "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1" for the start
+    pub fn from_fen(fen: &str) -> Result<Position, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 || fields.len() > 6 {
+            return Err(FenError::FieldCount(fields.len()));
+        }
+
+        let board = parse_board(fields[0])?;
+
+        let side_to_move = match fields[1] {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            side => return Err(FenError::InvalidSideToMove(side.to_string()))
+        };
+
+        let castling_rights = parse_castling_rights(fields[2], &board)?;
+        let en_passant = parse_en_passant(fields[3], &board, side_to_move)?;
+
+        let halfmove_clock = match fields.get(4) {
+            Some(clock) => clock.parse().map_err(|_| FenError::InvalidHalfmoveClock(clock.to_string()))?,
+            None => 0
+        };
+        let fullmove_number = match fields.get(5) {
+            Some(number) => match number.parse() {
+                Ok(number) if number > 0 => number,
+                _ => return Err(FenError::InvalidFullmoveNumber(number.to_string()))
+            },
+            None => 1
+        };
+
+        for color in [PieceColor::White, PieceColor::Black].iter() {
+            let count = board.pieces(*color, PieceType::King).count() as usize;
+            if count != 1 {
+                return Err(FenError::KingCount { color: *color, count });
+            }
+        }
+
+        let opponent = side_to_move.opposite();
+        if board.king_square(opponent).is_some_and(|king_square| board.is_attacked(king_square, side_to_move)) {
+            return Err(FenError::OpponentInCheck);
+        }
+
+        Ok(Position::from_parts(board, side_to_move, castling_rights, en_passant, halfmove_clock, fullmove_number))
+    }
+}
+
+// the ranks go from 8 down to 1, digits count empty squares
+fn parse_board(placement: &str) -> Result<Board, FenError> {
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::RankCount(ranks.len()));
+    }
+
+    let mut board = Board::empty();
+    for (i, rank_text) in ranks.iter().enumerate() {
+        let rank = 7 - i as u8;
+        let mut file = 0;
+
+        for c in rank_text.chars() {
+            if let Some(empty) = c.to_digit(10).filter(|digit| (1..=8).contains(digit)) {
+                file += empty as usize;
+                continue;
+            }
+
+            let piece = parse_piece(c).ok_or(FenError::InvalidPiece(c))?;
+            if file < 8 {
+                let square = Square::new(rank, file as u8);
+                if piece.piece_type == PieceType::Pawn && (rank == 0 || rank == 7) {
+                    return Err(FenError::PawnOnBackRank(square));
+                }
+                board.set(square, Some(piece));
+            }
+            file += 1;
+        }
+
+        if file != 8 {
+            return Err(FenError::RankLength { rank: rank + 1, squares: file });
+        }
+    }
+    Ok(board)
+}
+
+fn parse_piece(c: char) -> Option<Piece> {
+    let color = if c.is_ascii_uppercase() { PieceColor::White } else { PieceColor::Black };
+    let piece_type = match c.to_ascii_lowercase() {
+        'k' => PieceType::King,
+        'q' => PieceType::Queen,
+        'b' => PieceType::Bishop,
+        'n' => PieceType::Knight,
+        'r' => PieceType::Rook,
+        'p' => PieceType::Pawn,
+        _ => return None
+    };
+    Some(Piece::new(color, piece_type))
+}
+
+// every right needs the king and the rook on their starting squares
+fn parse_castling_rights(text: &str, board: &Board) -> Result<CastlingRights, FenError> {
+    let mut castling_rights = CastlingRights::NONE;
+    if text == "-" {
+        return Ok(castling_rights);
+    }
+
+    let error = || FenError::InvalidCastlingRights(text.to_string());
+    for c in text.chars() {
+        let (color, side) = match c {
+            'K' => (PieceColor::White, CastlingSide::Kingside),
+            'Q' => (PieceColor::White, CastlingSide::Queenside),
+            'k' => (PieceColor::Black, CastlingSide::Kingside),
+            'q' => (PieceColor::Black, CastlingSide::Queenside),
+            _ => return Err(error())
+        };
+
+        if castling_rights.has(color, side)
+        || board.piece_at(Square::new(color.home_rank(), 4)) != Some(Piece::new(color, PieceType::King))
+        || board.piece_at(Square::new(color.home_rank(), side.rook_file())) != Some(Piece::new(color, PieceType::Rook)) {
+            return Err(error());
+        }
+        castling_rights.add(color, side);
+    }
+    Ok(castling_rights)
+}
+
+// the square must be behind a pawn of the player who just moved, with the square it came from empty
+fn parse_en_passant(text: &str, board: &Board, side_to_move: PieceColor) -> Result<Option<Square>, FenError> {
+    if text == "-" {
+        return Ok(None);
+    }
+
+    let error = || FenError::InvalidEnPassant(text.to_string());
+    let square = parse_square(text).ok_or_else(error)?;
+
+    let opponent = side_to_move.opposite();
+    let expected_rank = (opponent.home_rank() as i8 + 2 * opponent.pawn_direction()) as u8;
+    if square.rank() != expected_rank {
+        return Err(error());
+    }
+
+    let direction = opponent.pawn_direction();
+    let pawn_square = square.offset(direction, 0).ok_or_else(error)?;
+    let start_square = square.offset(-direction, 0).ok_or_else(error)?;
+    if board.piece_at(square).is_some() || board.piece_at(start_square).is_some()
+    || board.piece_at(pawn_square) != Some(Piece::new(opponent, PieceType::Pawn)) {
+        return Err(error());
+    }
+    Ok(Some(square))
+}
+
+// a square like "e3"
+pub(crate) fn parse_square(text: &str) -> Option<Square> {
+    let mut chars = text.chars();
+    let file = chars.next()?;
+    let rank = chars.next()?;
+    if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+        return None;
+    }
+    Some(Square::new(rank as u8 - b'1', file as u8 - b'a'))
+}
+
+impl Position {
+    // writes the position in forsyth-edwards notation, the opposite of from_fen
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.piece_at(Square::new(rank, file)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece_char(piece));
+                    }
+                    None => empty += 1
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push_str(match self.side_to_move() {
+            PieceColor::White => " w ",
+            PieceColor::Black => " b "
+        });
+
+        let castling_rights = self.castling_rights();
+        let rights = [
+            (PieceColor::White, CastlingSide::Kingside, 'K'),
+            (PieceColor::White, CastlingSide::Queenside, 'Q'),
+            (PieceColor::Black, CastlingSide::Kingside, 'k'),
+            (PieceColor::Black, CastlingSide::Queenside, 'q'),
+        ];
+        let castling: String = rights.iter().filter(|(color, side, _)| castling_rights.has(*color, *side)).map(|(_, _, c)| *c).collect();
+        fen.push_str(if castling.is_empty() { "-" } else { &castling });
+
+        match self.en_passant() {
+            Some(square) => fen.push_str(&format!(" {}", square)),
+            None => fen.push_str(" -")
+        }
+
+        fen.push_str(&format!(" {} {}", self.halfmove_clock(), self.fullmove_number()));
+        fen
+    }
+}
+
+fn piece_char(piece: Piece) -> char {
+    let c = piece_letter(piece.piece_type);
+    match piece.color {
+        PieceColor::White => c,
+        PieceColor::Black => c.to_ascii_lowercase()
+    }
+}
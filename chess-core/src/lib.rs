@@ -0,0 +1,55 @@
+// chess rules without any rendering, the bevy app keeps its entities in sync with a `Position`
+
+mod types;
+pub use types::*;
+
+mod bitboard;
+pub use bitboard::*;
+
+mod attacks;
+pub use attacks::*;
+
+mod moves;
+pub use moves::*;
+
+mod board;
+
+mod zobrist;
+
+mod position;
+pub use position::*;
+
+mod movegen;
+
+mod fen;
+pub use fen::*;
+
+mod notation;
+pub use notation::*;
+
+mod pgn;
+pub use pgn::*;
+
+mod pgn_reader;
+pub use pgn_reader::*;
+
+mod eval;
+pub use eval::*;
+
+mod tt;
+pub use tt::*;
+
+mod search;
+pub use search::*;
+
+mod book;
+pub use book::*;
+
+mod uci;
+pub use uci::*;
+
+mod clock;
+pub use clock::*;
+
+mod engine;
+pub use engine::*;
@@ -0,0 +1,107 @@
+use crate::{attacks::*, moves::*, position::*, types::*};
+
+const PROMOTION_TYPES: [PieceType; 4] = [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight];
+
+impl Position {
+    // all moves the player to move can play, with their flags set
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(64);
+        let own = self.board.color(self.side_to_move);
+        let opponent = self.board.color(self.side_to_move.opposite());
+
+        for from in own {
+            let piece = self.piece_at(from).expect("bitboards out of sync");
+            if piece.piece_type == PieceType::Pawn {
+                self.pawn_moves(from, &mut moves);
+                continue;
+            }
+
+            for to in self.board.piece_attacks(from, piece) & !own {
+                let flags = if opponent.contains(to) { MoveFlags::CAPTURE } else { MoveFlags::NONE };
+                moves.push(Move { from, to, promotion: None, flags });
+            }
+        }
+        self.castling_moves(&mut moves);
+
+        moves.retain(|mv| self.is_king_safe_after(*mv));
+        moves
+    }
+
+    // the legal moves of the piece on the square, a promotion comes once for every piece it can turn into
+    pub fn legal_moves_from(&self, from: Square) -> Vec<Move> {
+        let mut moves = self.legal_moves();
+        moves.retain(|mv| mv.from == from);
+        moves
+    }
+
+    // counts the leaf nodes of the move tree to the given depth, used to test the move generator
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let moves = self.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+
+        let mut nodes = 0;
+        for mv in moves {
+            self.make_move(mv);
+            nodes += self.perft(depth - 1);
+            self.unmake_move();
+        }
+        nodes
+    }
+
+    fn pawn_moves(&self, from: Square, moves: &mut Vec<Move>) {
+        let color = self.side_to_move;
+        let direction = color.pawn_direction();
+
+        // a pawn reaching the last rank adds one move for every piece it can turn into
+        let mut push = |to: Square, flags: MoveFlags| {
+            if to.rank() == color.opposite().home_rank() {
+                for promotion in PROMOTION_TYPES.iter() {
+                    moves.push(Move { from, to, promotion: Some(*promotion), flags });
+                }
+            } else {
+                moves.push(Move { from, to, promotion: None, flags });
+            }
+        };
+
+        let occupied = self.board.occupied();
+        if let Some(to) = from.offset(direction, 0).filter(|to| !occupied.contains(*to)) {
+            push(to, MoveFlags::NONE);
+
+            // two squares only from the starting rank
+            if from.rank() as i8 == color.home_rank() as i8 + direction {
+                if let Some(to) = to.offset(direction, 0).filter(|to| !occupied.contains(*to)) {
+                    push(to, MoveFlags::DOUBLE_PUSH);
+                }
+            }
+        }
+
+        let opponent = self.board.color(color.opposite());
+        for to in pawn_attacks(color, from) {
+            if opponent.contains(to) {
+                push(to, MoveFlags::CAPTURE);
+            } else if Some(to) == self.en_passant {
+                push(to, MoveFlags::CAPTURE | MoveFlags::EN_PASSANT);
+            }
+        }
+    }
+
+    fn castling_moves(&self, moves: &mut Vec<Move>) {
+        let home_rank = self.side_to_move.home_rank();
+        for side in [CastlingSide::Kingside, CastlingSide::Queenside].iter() {
+            if self.is_castling_possible(*side) {
+                moves.push(Move {
+                    from: Square::new(home_rank, 4),
+                    to: Square::new(home_rank, side.king_to_file()),
+                    promotion: None,
+                    flags: MoveFlags::CASTLING,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::hash::{Hash, Hasher};
+use std::ops::BitOr;
+
+use crate::types::*;
+
+// what kind of move it is, set by the move generator
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct MoveFlags(u8);
+
+impl MoveFlags {
+    pub const NONE: MoveFlags = MoveFlags(0);
+    pub const CAPTURE: MoveFlags = MoveFlags(0b0001);
+    pub const DOUBLE_PUSH: MoveFlags = MoveFlags(0b0010);
+    // always comes together with CAPTURE
+    pub const EN_PASSANT: MoveFlags = MoveFlags(0b0100);
+    pub const CASTLING: MoveFlags = MoveFlags(0b1000);
+
+    pub fn contains(self, flags: MoveFlags) -> bool {
+        self.0 & flags.0 == flags.0
+    }
+}
+
+impl BitOr for MoveFlags {
+    type Output = MoveFlags;
+
+    fn bitor(self, other: MoveFlags) -> MoveFlags {
+        MoveFlags(self.0 | other.0)
+    }
+}
+
+// moves are equal when they go from and to the same squares with the same promotion, whatever their flags
+#[derive(Clone, Copy, Debug)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    // piece a pawn turns into on the last rank
+    pub promotion: Option<PieceType>,
+    // the position doesn't look at them, moves built by hand can leave them empty
+    pub flags: MoveFlags,
+}
+
+impl Move {
+    pub fn new(from: Square, to: Square) -> Move {
+        Move { from, to, promotion: None, flags: MoveFlags::NONE }
+    }
+
+    pub fn with_promotion(from: Square, to: Square, promotion: PieceType) -> Move {
+        Move { from, to, promotion: Some(promotion), flags: MoveFlags::NONE }
+    }
+
+    pub fn is_capture(self) -> bool {
+        self.flags.contains(MoveFlags::CAPTURE)
+    }
+
+    pub fn is_double_push(self) -> bool {
+        self.flags.contains(MoveFlags::DOUBLE_PUSH)
+    }
+
+    pub fn is_en_passant(self) -> bool {
+        self.flags.contains(MoveFlags::EN_PASSANT)
+    }
+
+    pub fn is_castling(self) -> bool {
+        self.flags.contains(MoveFlags::CASTLING)
+    }
+}
+
+impl PartialEq for Move {
+    fn eq(&self, other: &Move) -> bool {
+        self.from == other.from && self.to == other.to && self.promotion == other.promotion
+    }
+}
+
+impl Eq for Move {}
+
+impl Hash for Move {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.from.hash(state);
+        self.to.hash(state);
+        self.promotion.hash(state);
+    }
+}
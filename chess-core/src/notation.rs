@@ -0,0 +1,281 @@
+use std::{error::Error, fmt};
+
+use crate::{fen::*, moves::*, position::*, types::*};
+
+impl fmt::Display for Move {
+    // uci notation
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        if let Some(promotion) = self.promotion {
+            write!(f, "{}", piece_letter(promotion).to_ascii_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+impl Position {
+    // standard algebraic notation of a legal move, like "Nbd7", "exd6", "e8=Q+" or "O-O-O#"
+    pub fn san(&self, mv: Move) -> String {
+        let piece = self.piece_at(mv.from).expect("no piece to move");
+        let mut san = String::new();
+
+        if let Some(side) = castling_side(piece, mv) {
+            san.push_str(match side {
+                CastlingSide::Kingside => "O-O",
+                CastlingSide::Queenside => "O-O-O"
+            });
+        } else if piece.piece_type == PieceType::Pawn {
+            // pawn captures name the file the pawn came from
+            if mv.from.file() != mv.to.file() {
+                san.push(file_char(mv.from));
+                san.push('x');
+            }
+            san.push_str(&mv.to.to_string());
+            if let Some(promotion) = mv.promotion {
+                san.push('=');
+                san.push(piece_letter(promotion));
+            }
+        } else {
+            san.push(piece_letter(piece.piece_type));
+
+            // other pieces of the same type that can go to the same square, the file tells them apart
+            // if it can, otherwise the rank, otherwise both
+            let others: Vec<Move> = self.legal_moves().into_iter()
+                .filter(|other| other.to == mv.to && other.from != mv.from && self.piece_at(other.from) == Some(piece))
+                .collect();
+            if !others.is_empty() {
+                if others.iter().all(|other| other.from.file() != mv.from.file()) {
+                    san.push(file_char(mv.from));
+                } else if others.iter().all(|other| other.from.rank() != mv.from.rank()) {
+                    san.push(rank_char(mv.from));
+                } else {
+                    san.push(file_char(mv.from));
+                    san.push(rank_char(mv.from));
+                }
+            }
+
+            if self.piece_at(mv.to).is_some() {
+                san.push('x');
+            }
+            san.push_str(&mv.to.to_string());
+        }
+
+        san.push_str(self.check_suffix(mv));
+        san
+    }
+
+    // long algebraic notation names the square the piece comes from, like "Ng1-f3", "e4xd5" or "e7-e8=Q+"
+    pub fn long_algebraic(&self, mv: Move) -> String {
+        let piece = self.piece_at(mv.from).expect("no piece to move");
+        let mut lan = String::new();
+
+        if let Some(side) = castling_side(piece, mv) {
+            lan.push_str(match side {
+                CastlingSide::Kingside => "O-O",
+                CastlingSide::Queenside => "O-O-O"
+            });
+        } else {
+            if piece.piece_type != PieceType::Pawn {
+                lan.push(piece_letter(piece.piece_type));
+            }
+            let capture = self.piece_at(mv.to).is_some() || (piece.piece_type == PieceType::Pawn && mv.from.file() != mv.to.file());
+            lan.push_str(&format!("{}{}{}", mv.from, if capture { 'x' } else { '-' }, mv.to));
+            if let Some(promotion) = mv.promotion {
+                lan.push('=');
+                lan.push(piece_letter(promotion));
+            }
+        }
+
+        lan.push_str(self.check_suffix(mv));
+        lan
+    }
+
+    // "+" if the move gives check, "#" if it checkmates
+    fn check_suffix(&self, mv: Move) -> &'static str {
+        // the history of the game isn't needed for check and mate, copying it would make every san cost as much as the game is long
+        let mut after = self.without_history();
+        after.make_move(mv);
+        if !after.is_check() {
+            ""
+        } else if after.has_legal_moves() {
+            "+"
+        } else {
+            "#"
+        }
+    }
+}
+
+// the letter of the piece in english notation, pawns have none in san but use 'P' elsewhere
+pub(crate) fn piece_letter(piece_type: PieceType) -> char {
+    match piece_type {
+        PieceType::King => 'K',
+        PieceType::Queen => 'Q',
+        PieceType::Bishop => 'B',
+        PieceType::Knight => 'N',
+        PieceType::Rook => 'R',
+        PieceType::Pawn => 'P',
+    }
+}
+
+fn file_char(square: Square) -> char {
+    (b'a' + square.file()) as char
+}
+
+fn rank_char(square: Square) -> char {
+    (b'1' + square.rank()) as char
+}
+
+// why a move in san, long algebraic or uci notation couldn't be turned into a move
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum NotationError {
+    // not written like a move
+    Invalid(String),
+    // no legal move fits it
+    Illegal(String),
+    // more than one legal move fits it
+    Ambiguous(String),
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NotationError::Invalid(text) => write!(f, "'{}' is not written like a move", text),
+            NotationError::Illegal(text) => write!(f, "{} is not a legal move in this position", text),
+            NotationError::Ambiguous(text) => write!(f, "{} fits more than one legal move, add the file or rank the piece comes from", text),
+        }
+    }
+}
+
+impl Error for NotationError {}
+
+impl Position {
+    // finds the legal move for a san string, checks and annotations at the end are ignored
+    pub fn parse_san(&self, san: &str) -> Result<Move, NotationError> {
+        let text = san.trim_end_matches(['+', '#', '!', '?']);
+        let invalid = || NotationError::Invalid(san.to_string());
+
+        let candidates: Vec<Move> = if text == "O-O" || text == "0-0" || text == "O-O-O" || text == "0-0-0" {
+            let king_file = if text.len() == 3 { CastlingSide::Kingside.king_to_file() } else { CastlingSide::Queenside.king_to_file() };
+            self.legal_moves().into_iter().filter(|mv| mv.is_castling() && mv.to.file() == king_file).collect()
+        } else {
+            let mut chars: Vec<char> = text.chars().collect();
+
+            let piece_type = match chars.first() {
+                Some('K') => PieceType::King,
+                Some('Q') => PieceType::Queen,
+                Some('R') => PieceType::Rook,
+                Some('B') => PieceType::Bishop,
+                Some('N') => PieceType::Knight,
+                Some('a'..='h') => PieceType::Pawn,
+                _ => return Err(invalid())
+            };
+            if piece_type != PieceType::Pawn {
+                chars.remove(0);
+            }
+
+            // the promotion is written as "e8=Q", sometimes without the '='
+            let mut promotion = None;
+            if let Some(last) = chars.last().copied() {
+                if let Some(promotion_type) = promotion_type(last) {
+                    chars.pop();
+                    if chars.last() == Some(&'=') {
+                        chars.pop();
+                    }
+                    promotion = Some(promotion_type);
+                }
+            }
+
+            if chars.len() < 2 {
+                return Err(invalid());
+            }
+            let to_text: String = chars.split_off(chars.len() - 2).into_iter().collect();
+            let to = parse_square(&to_text).ok_or_else(invalid)?;
+
+            if chars.last() == Some(&'x') {
+                chars.pop();
+            }
+
+            // what is left tells apart pieces that can go to the same square
+            let (from_file, from_rank) = match chars.as_slice() {
+                [] => (None, None),
+                [file @ 'a'..='h'] => (Some(*file as u8 - b'a'), None),
+                [rank @ '1'..='8'] => (None, Some(*rank as u8 - b'1')),
+                [file @ 'a'..='h', rank @ '1'..='8'] => (Some(*file as u8 - b'a'), Some(*rank as u8 - b'1')),
+                _ => return Err(invalid())
+            };
+
+            self.legal_moves().into_iter().filter(|mv| {
+                mv.to == to && mv.promotion == promotion && !mv.is_castling()
+                    && self.piece_at(mv.from).map(|piece| piece.piece_type) == Some(piece_type)
+                    && from_file.is_none_or(|file| mv.from.file() == file)
+                    && from_rank.is_none_or(|rank| mv.from.rank() == rank)
+            }).collect()
+        };
+
+        match candidates.as_slice() {
+            [mv] => Ok(*mv),
+            [] => Err(NotationError::Illegal(san.to_string())),
+            _ => Err(NotationError::Ambiguous(san.to_string()))
+        }
+    }
+}
+
+impl Position {
+    // finds the legal move for long algebraic notation, the '-' and 'x' between the squares are optional
+    pub fn parse_long_algebraic(&self, lan: &str) -> Result<Move, NotationError> {
+        let text = lan.trim_end_matches(['+', '#', '!', '?']);
+        if text == "O-O" || text == "0-0" || text == "O-O-O" || text == "0-0-0" {
+            return self.parse_san(lan);
+        }
+
+        let mut chars: Vec<char> = text.chars().filter(|c| *c != '-' && *c != 'x' && *c != '=').collect();
+        let piece_type = match chars.first().copied().and_then(promotion_type) {
+            Some(piece_type) => {
+                chars.remove(0);
+                piece_type
+            }
+            None if chars.first() == Some(&'K') => {
+                chars.remove(0);
+                PieceType::King
+            }
+            None => PieceType::Pawn
+        };
+
+        let (from, to, promotion) = parse_coordinates(&chars, promotion_type).ok_or_else(|| NotationError::Invalid(lan.to_string()))?;
+        self.find_legal_move(from, to, promotion)
+            .filter(|mv| self.piece_at(mv.from).map(|piece| piece.piece_type) == Some(piece_type))
+            .ok_or_else(|| NotationError::Illegal(lan.to_string()))
+    }
+
+    // finds the legal move for uci notation, like "e2e4", "e7e8q" or "e1g1" for castling
+    pub fn parse_uci(&self, uci: &str) -> Result<Move, NotationError> {
+        let chars: Vec<char> = uci.chars().collect();
+        let (from, to, promotion) = parse_coordinates(&chars, |c| promotion_type(c.to_ascii_uppercase()))
+            .ok_or_else(|| NotationError::Invalid(uci.to_string()))?;
+        self.find_legal_move(from, to, promotion).ok_or_else(|| NotationError::Illegal(uci.to_string()))
+    }
+
+    fn find_legal_move(&self, from: Square, to: Square, promotion: Option<PieceType>) -> Option<Move> {
+        self.legal_moves().into_iter().find(|mv| mv.from == from && mv.to == to && mv.promotion == promotion)
+    }
+}
+
+// two squares and an optional promotion letter, like "e7e8q"
+fn parse_coordinates(chars: &[char], promotion: impl Fn(char) -> Option<PieceType>) -> Option<(Square, Square, Option<PieceType>)> {
+    let square = |chars: &[char]| parse_square(&chars.iter().collect::<String>());
+    match chars {
+        [_, _, _, _] => Some((square(&chars[0..2])?, square(&chars[2..4])?, None)),
+        [_, _, _, _, letter] => Some((square(&chars[0..2])?, square(&chars[2..4])?, Some(promotion(*letter)?))),
+        _ => None
+    }
+}
+
+fn promotion_type(c: char) -> Option<PieceType> {
+    match c {
+        'Q' => Some(PieceType::Queen),
+        'R' => Some(PieceType::Rook),
+        'B' => Some(PieceType::Bishop),
+        'N' => Some(PieceType::Knight),
+        _ => None
+    }
+}
@@ -0,0 +1,210 @@
+use std::{fmt, time::Duration};
+
+use crate::{moves::*, position::*, types::*};
+
+// the result token at the end of the moves and in the Result tag
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PgnResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    // the game is still running or was abandoned
+    Unfinished,
+}
+
+impl PgnResult {
+    pub fn from_game_end(game_end: Option<GameEnd>) -> PgnResult {
+        match game_end {
+            Some(GameEnd::Aborted) => PgnResult::Unfinished,
+            Some(game_end) => match game_end.winner() {
+                Some(PieceColor::White) => PgnResult::WhiteWins,
+                Some(PieceColor::Black) => PgnResult::BlackWins,
+                None => PgnResult::Draw
+            },
+            None => PgnResult::Unfinished
+        }
+    }
+}
+
+impl GameEnd {
+    // value of the Termination tag, one of the reasons the pgn standard names
+    pub fn termination(self) -> &'static str {
+        match self {
+            GameEnd::Timeout(_) | GameEnd::TimeoutVsInsufficientMaterial => "time forfeit",
+            GameEnd::Aborted => "abandoned",
+            _ => "normal"
+        }
+    }
+}
+
+impl fmt::Display for PgnResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            PgnResult::WhiteWins => "1-0",
+            PgnResult::BlackWins => "0-1",
+            PgnResult::Draw => "1/2-1/2",
+            PgnResult::Unfinished => "*",
+        })
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PgnMove {
+    pub mv: Move,
+    // comment in front of the first move of the game or of a variation
+    pub comment_before: Option<String>,
+    // numeric annotation glyphs like 1 for "!" or 4 for "??", written as $1 after the move
+    pub nags: Vec<u8>,
+    // written in braces after the move
+    pub comment: Option<String>,
+    // other moves that could have been played instead of this one
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    pub fn new(mv: Move) -> PgnMove {
+        PgnMove { mv, comment_before: None, nags: Vec::new(), comment: None, variations: Vec::new() }
+    }
+}
+
+// a game with its tags and moves, written in portable game notation
+#[derive(Clone)]
+pub struct PgnGame {
+    // tag pairs in the order they are written, the seven tag roster comes first
+    pub tags: Vec<(String, String)>,
+    pub start: Position,
+    pub moves: Vec<PgnMove>,
+    pub result: PgnResult,
+}
+
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [("Event", "?"), ("Site", "?"), ("Date", "????.??.??"), ("Round", "?"), ("White", "?"), ("Black", "?"), ("Result", "*")];
+
+// lines of the move text are kept shorter than this
+const LINE_LENGTH: usize = 80;
+
+impl PgnGame {
+    // a game without moves, games that don't start from the standard setup get a SetUp and FEN tag
+    pub fn new(start: Position) -> PgnGame {
+        let mut game = PgnGame {
+            tags: SEVEN_TAG_ROSTER.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            start,
+            moves: Vec::new(),
+            result: PgnResult::Unfinished,
+        };
+
+        let fen = game.start.to_fen();
+        if fen != Position::new().to_fen() {
+            game.set_tag("SetUp", "1");
+            game.set_tag("FEN", &fen);
+        }
+        game
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+
+    // replaces the value of a tag or adds it at the end
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string()))
+        }
+    }
+
+    pub fn push(&mut self, mv: Move, comment: Option<String>) {
+        self.moves.push(PgnMove { comment, ..PgnMove::new(mv) });
+    }
+
+    // the position after all moves
+    pub fn end_position(&self) -> Position {
+        let mut position = self.start.clone();
+        for pgn_move in self.moves.iter() {
+            position.make_move(pgn_move.mv);
+        }
+        position
+    }
+
+    // writes the tags and the moves in san, the Result tag always matches the result
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        for (name, value) in self.tags.iter() {
+            let value = if name == "Result" { self.result.to_string() } else { value.replace('\\', "\\\\").replace('"', "\\\"") };
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+        }
+        pgn.push('\n');
+
+        let mut tokens = Vec::new();
+        write_moves(&self.start, &self.moves, &mut tokens);
+        tokens.push(self.result.to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() >= LINE_LENGTH {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+        pgn
+    }
+}
+
+// adds the tokens for the moves and their variations, the position is the one before the first move
+fn write_moves(start: &Position, moves: &[PgnMove], tokens: &mut Vec<String>) {
+    let mut position = start.clone();
+    // the move number is needed before every white move and before black moves after a comment or variation
+    let mut needs_number = true;
+    for pgn_move in moves {
+        if let Some(comment) = &pgn_move.comment_before {
+            tokens.push(comment_token(comment));
+        }
+
+        // the number stays on the same line as its move
+        let number = position.fullmove_number();
+        let san = position.san(pgn_move.mv);
+        tokens.push(match position.side_to_move() {
+            PieceColor::White => format!("{}. {}", number, san),
+            PieceColor::Black if needs_number => format!("{}... {}", number, san),
+            PieceColor::Black => san
+        });
+        tokens.extend(pgn_move.nags.iter().map(|nag| format!("${}", nag)));
+
+        needs_number = false;
+        if let Some(comment) = &pgn_move.comment {
+            tokens.push(comment_token(comment));
+            needs_number = true;
+        }
+        for variation in pgn_move.variations.iter() {
+            let mut variation_tokens = Vec::new();
+            write_moves(&position, variation, &mut variation_tokens);
+            if let Some(first) = variation_tokens.first_mut() {
+                first.insert(0, '(');
+            }
+            if let Some(last) = variation_tokens.last_mut() {
+                last.push(')');
+            }
+            tokens.extend(variation_tokens);
+            needs_number = true;
+        }
+
+        position.make_move(pgn_move.mv);
+    }
+}
+
+// braces can't be nested, a closing brace would end the comment early
+fn comment_token(comment: &str) -> String {
+    format!("{{{}}}", comment.replace('}', ")"))
+}
+
+// comment with the time left on the clock of the player who moved, like "[%clk 0:04:59]"
+pub fn clock_comment(time_left: Duration) -> String {
+    let seconds = time_left.as_secs();
+    format!("[%clk {}:{:02}:{:02}]", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
@@ -0,0 +1,359 @@
+use std::{error::Error, fmt};
+
+use crate::{fen::*, notation::*, pgn::*, position::*};
+
+// what is wrong in a pgn file
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PgnErrorKind {
+    UnexpectedCharacter(char),
+    UnterminatedComment,
+    UnterminatedString,
+    // a tag has to look like [Name "value"]
+    MalformedTag,
+    InvalidFen(FenError),
+    InvalidMove(NotationError),
+    InvalidNag(String),
+    // a token that doesn't belong where it is, like a result inside a variation
+    UnexpectedToken(String),
+    // a NAG, suffix or variation needs a move before it
+    MissingMove(String),
+    UnclosedVariation,
+    UnmatchedVariationEnd,
+}
+
+// where the problem is, lines and columns count from 1
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PgnError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: PgnErrorKind,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            PgnErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character '{}'", c),
+            PgnErrorKind::UnterminatedComment => write!(f, "comment is missing its closing '}}'"),
+            PgnErrorKind::UnterminatedString => write!(f, "tag value is missing its closing '\"'"),
+            PgnErrorKind::MalformedTag => write!(f, "tags must look like [Name \"value\"]"),
+            PgnErrorKind::InvalidFen(error) => write!(f, "invalid FEN tag: {}", error),
+            PgnErrorKind::InvalidMove(error) => write!(f, "{}", error),
+            PgnErrorKind::InvalidNag(nag) => write!(f, "'{}' is not a numeric annotation glyph between $0 and $255", nag),
+            PgnErrorKind::UnexpectedToken(token) => write!(f, "unexpected '{}'", token),
+            PgnErrorKind::MissingMove(token) => write!(f, "'{}' needs a move before it", token),
+            PgnErrorKind::UnclosedVariation => write!(f, "variation is missing its closing ')'"),
+            PgnErrorKind::UnmatchedVariationEnd => write!(f, "')' without a variation to close"),
+        }
+    }
+}
+
+impl Error for PgnError {}
+
+// reads all games of a pgn file
+pub fn read_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser { tokens, index: 0 };
+
+    let mut games = Vec::new();
+    while parser.peek().is_some() {
+        games.push(parser.game()?);
+    }
+    Ok(games)
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    TagStart,
+    TagEnd,
+    // moves, move numbers, results and tag names
+    Symbol(String),
+    Str(String),
+    Period,
+    Comment(String),
+    Nag(u8),
+    // "!", "?", "!!", "??", "!?" or "?!" after a move
+    Suffix(String),
+    VariationStart,
+    VariationEnd,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::TagStart => write!(f, "["),
+            Token::TagEnd => write!(f, "]"),
+            Token::Symbol(symbol) | Token::Suffix(symbol) => write!(f, "{}", symbol),
+            Token::Str(value) => write!(f, "\"{}\"", value),
+            Token::Period => write!(f, "."),
+            Token::Comment(comment) => write!(f, "{{{}}}", comment),
+            Token::Nag(nag) => write!(f, "${}", nag),
+            Token::VariationStart => write!(f, "("),
+            Token::VariationEnd => write!(f, ")"),
+        }
+    }
+}
+
+// token with the line and column it starts at
+struct Spanned {
+    token: Token,
+    line: usize,
+    column: usize,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Spanned>, PgnError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let (mut i, mut line, mut column) = (0, 1, 1);
+
+    // moves on by one character and keeps track of the line and column
+    let advance = |i: &mut usize, line: &mut usize, column: &mut usize| {
+        if chars[*i] == '\n' {
+            *line += 1;
+            *column = 1;
+        } else {
+            *column += 1;
+        }
+        *i += 1;
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let (start_line, start_column) = (line, column);
+        let error = |kind| PgnError { line: start_line, column: start_column, kind };
+
+        if c.is_whitespace() {
+            advance(&mut i, &mut line, &mut column);
+            continue;
+        }
+
+        // lines starting with '%' and everything after ';' are skipped
+        if (c == '%' && column == 1) || c == ';' {
+            while i < chars.len() && chars[i] != '\n' {
+                advance(&mut i, &mut line, &mut column);
+            }
+            continue;
+        }
+
+        let token = match c {
+            '[' => { advance(&mut i, &mut line, &mut column); Token::TagStart }
+            ']' => { advance(&mut i, &mut line, &mut column); Token::TagEnd }
+            '(' => { advance(&mut i, &mut line, &mut column); Token::VariationStart }
+            ')' => { advance(&mut i, &mut line, &mut column); Token::VariationEnd }
+            '.' => { advance(&mut i, &mut line, &mut column); Token::Period }
+            '*' => { advance(&mut i, &mut line, &mut column); Token::Symbol("*".to_string()) }
+            '{' => {
+                advance(&mut i, &mut line, &mut column);
+                let mut comment = String::new();
+                loop {
+                    match chars.get(i) {
+                        Some('}') => break,
+                        Some(c) => comment.push(*c),
+                        None => return Err(error(PgnErrorKind::UnterminatedComment))
+                    }
+                    advance(&mut i, &mut line, &mut column);
+                }
+                advance(&mut i, &mut line, &mut column);
+                Token::Comment(comment.split_whitespace().collect::<Vec<_>>().join(" "))
+            }
+            '"' => {
+                advance(&mut i, &mut line, &mut column);
+                let mut value = String::new();
+                loop {
+                    match chars.get(i) {
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.get(i + 1), Some('"') | Some('\\')) => {
+                            advance(&mut i, &mut line, &mut column);
+                            value.push(chars[i]);
+                        }
+                        Some('\n') | None => return Err(error(PgnErrorKind::UnterminatedString)),
+                        Some(c) => value.push(*c)
+                    }
+                    advance(&mut i, &mut line, &mut column);
+                }
+                advance(&mut i, &mut line, &mut column);
+                Token::Str(value)
+            }
+            '$' => {
+                advance(&mut i, &mut line, &mut column);
+                let mut digits = String::new();
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    digits.push(chars[i]);
+                    advance(&mut i, &mut line, &mut column);
+                }
+                let nag = digits.parse().map_err(|_| error(PgnErrorKind::InvalidNag(format!("${}", digits))))?;
+                Token::Nag(nag)
+            }
+            '!' | '?' => {
+                let mut suffix = String::new();
+                while i < chars.len() && (chars[i] == '!' || chars[i] == '?') {
+                    suffix.push(chars[i]);
+                    advance(&mut i, &mut line, &mut column);
+                }
+                Token::Suffix(suffix)
+            }
+            c if c.is_ascii_alphanumeric() => {
+                let mut symbol = String::new();
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || "_+#=:-/".contains(chars[i])) {
+                    symbol.push(chars[i]);
+                    advance(&mut i, &mut line, &mut column);
+                }
+                Token::Symbol(symbol)
+            }
+            c => return Err(error(PgnErrorKind::UnexpectedCharacter(c)))
+        };
+        tokens.push(Spanned { token, line: start_line, column: start_column });
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    index: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|spanned| &spanned.token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).map(|spanned| spanned.token.clone());
+        self.index += 1;
+        token
+    }
+
+    // error at the token that was just read, or at the end of the file
+    fn error(&self, kind: PgnErrorKind) -> PgnError {
+        let spanned = self.tokens.get(self.index.saturating_sub(1)).or_else(|| self.tokens.last());
+        PgnError {
+            line: spanned.map_or(1, |spanned| spanned.line),
+            column: spanned.map_or(1, |spanned| spanned.column),
+            kind,
+        }
+    }
+
+    fn game(&mut self) -> Result<PgnGame, PgnError> {
+        let mut tags = Vec::new();
+        let mut fen = None;
+        while self.peek() == Some(&Token::TagStart) {
+            self.next();
+            let name = match self.next() {
+                Some(Token::Symbol(name)) => name,
+                _ => return Err(self.error(PgnErrorKind::MalformedTag))
+            };
+            let value = match self.next() {
+                Some(Token::Str(value)) => value,
+                _ => return Err(self.error(PgnErrorKind::MalformedTag))
+            };
+            if name == "FEN" {
+                fen = Some(Position::from_fen(&value).map_err(|error| self.error(PgnErrorKind::InvalidFen(error)))?);
+            }
+            if self.next() != Some(Token::TagEnd) {
+                return Err(self.error(PgnErrorKind::MalformedTag));
+            }
+            tags.push((name, value));
+        }
+
+        let start = fen.unwrap_or_default();
+        let (moves, result) = self.moves(&start, false)?;
+
+        // the result at the end of the moves wins over the tag, games without one keep the tag
+        let result = result.unwrap_or_else(|| match tags.iter().find(|(name, _)| name == "Result").map(|(_, value)| value.as_str()) {
+            Some("1-0") => PgnResult::WhiteWins,
+            Some("0-1") => PgnResult::BlackWins,
+            Some("1/2-1/2") => PgnResult::Draw,
+            _ => PgnResult::Unfinished
+        });
+        Ok(PgnGame { tags, start, moves, result })
+    }
+
+    // reads the moves of the game or a variation until its end, returns the result if the game ends with one
+    fn moves(&mut self, start: &Position, in_variation: bool) -> Result<(Vec<PgnMove>, Option<PgnResult>), PgnError> {
+        let mut moves: Vec<PgnMove> = Vec::new();
+        let mut position = start.clone();
+        // variations start from the position before the last move
+        let mut before_last = start.clone();
+        let mut comment_before: Option<String> = None;
+
+        loop {
+            let token = match self.peek() {
+                Some(Token::TagStart) | None if in_variation => {
+                    self.next();
+                    return Err(self.error(PgnErrorKind::UnclosedVariation));
+                }
+                Some(Token::TagStart) | None => return Ok((moves, None)),
+                Some(_) => self.next().unwrap()
+            };
+
+            match token {
+                Token::Comment(comment) => {
+                    let target = match moves.last_mut() {
+                        Some(last) => &mut last.comment,
+                        None => &mut comment_before
+                    };
+                    *target = Some(match target.take() {
+                        Some(previous) => format!("{} {}", previous, comment),
+                        None => comment
+                    });
+                }
+                Token::Nag(nag) => match moves.last_mut() {
+                    Some(last) => last.nags.push(nag),
+                    None => return Err(self.error(PgnErrorKind::MissingMove(token.to_string())))
+                },
+                Token::Suffix(suffix) => {
+                    let nag = match suffix.as_str() {
+                        "!" => 1,
+                        "?" => 2,
+                        "!!" => 3,
+                        "??" => 4,
+                        "!?" => 5,
+                        "?!" => 6,
+                        _ => return Err(self.error(PgnErrorKind::UnexpectedToken(suffix)))
+                    };
+                    match moves.last_mut() {
+                        Some(last) => last.nags.push(nag),
+                        None => return Err(self.error(PgnErrorKind::MissingMove(suffix)))
+                    }
+                }
+                Token::Period => {}
+                Token::VariationStart => {
+                    if moves.is_empty() {
+                        return Err(self.error(PgnErrorKind::MissingMove(token.to_string())));
+                    }
+                    let (variation, _) = self.moves(&before_last, true)?;
+                    moves.last_mut().unwrap().variations.push(variation);
+                }
+                Token::VariationEnd if in_variation => return Ok((moves, None)),
+                Token::VariationEnd => return Err(self.error(PgnErrorKind::UnmatchedVariationEnd)),
+                Token::Symbol(symbol) => {
+                    let result = match symbol.as_str() {
+                        "1-0" => Some(PgnResult::WhiteWins),
+                        "0-1" => Some(PgnResult::BlackWins),
+                        "1/2-1/2" => Some(PgnResult::Draw),
+                        "*" => Some(PgnResult::Unfinished),
+                        _ => None
+                    };
+                    if let Some(result) = result {
+                        if in_variation {
+                            return Err(self.error(PgnErrorKind::UnexpectedToken(symbol)));
+                        }
+                        return Ok((moves, Some(result)));
+                    }
+
+                    // move numbers are only there for people reading the file
+                    if symbol.chars().all(|c| c.is_ascii_digit()) {
+                        continue;
+                    }
+
+                    let mv = position.parse_san(&symbol).map_err(|error| self.error(PgnErrorKind::InvalidMove(error)))?;
+                    // the moves before it aren't needed to read a variation, copying them would make the game cost its length squared
+                    before_last = position.without_history();
+                    position.make_move(mv);
+                    moves.push(PgnMove { mv, comment_before: comment_before.take(), ..PgnMove::new(mv) });
+                }
+                Token::TagStart | Token::TagEnd | Token::Str(_) => return Err(self.error(PgnErrorKind::UnexpectedToken(token.to_string())))
+            }
+        }
+    }
+}
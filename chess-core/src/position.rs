@@ -0,0 +1,527 @@
+use crate::{bitboard::*, board::*, moves::*, types::*, zobrist::*};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CastlingSide {
+    Kingside,
+    Queenside,
+}
+
+impl CastlingSide {
+    // file of the rook before castling
+    pub fn rook_file(self) -> u8 {
+        match self {
+            CastlingSide::Kingside => 7,
+            CastlingSide::Queenside => 0
+        }
+    }
+
+    // files of the king and the rook after castling
+    pub fn king_to_file(self) -> u8 {
+        match self {
+            CastlingSide::Kingside => 6,
+            CastlingSide::Queenside => 2
+        }
+    }
+
+    pub fn rook_to_file(self) -> u8 {
+        match self {
+            CastlingSide::Kingside => 5,
+            CastlingSide::Queenside => 3
+        }
+    }
+}
+
+// which kings can still castle to which side
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CastlingRights(u8);
+
+impl CastlingRights {
+    pub const NONE: CastlingRights = CastlingRights(0);
+    pub const ALL: CastlingRights = CastlingRights(0b1111);
+
+    fn bit(color: PieceColor, side: CastlingSide) -> u8 {
+        match (color, side) {
+            (PieceColor::White, CastlingSide::Kingside) => 0b0001,
+            (PieceColor::White, CastlingSide::Queenside) => 0b0010,
+            (PieceColor::Black, CastlingSide::Kingside) => 0b0100,
+            (PieceColor::Black, CastlingSide::Queenside) => 0b1000,
+        }
+    }
+
+    pub fn has(self, color: PieceColor, side: CastlingSide) -> bool {
+        self.0 & CastlingRights::bit(color, side) != 0
+    }
+
+    pub fn add(&mut self, color: PieceColor, side: CastlingSide) {
+        self.0 |= CastlingRights::bit(color, side);
+    }
+
+    pub fn remove(&mut self, color: PieceColor, side: CastlingSide) {
+        self.0 &= !CastlingRights::bit(color, side);
+    }
+
+    pub(crate) fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+// the ways a game can end
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GameEnd {
+    // the winner checkmated the other king
+    Checkmate(PieceColor),
+    // the player to move has no legal moves but isn't in check
+    Stalemate,
+    // neither player has the pieces left to checkmate
+    InsufficientMaterial,
+    // claimed after fifty moves of each player without a capture or pawn move
+    FiftyMoveRule,
+    // seventy-five moves of each player without a capture or pawn move
+    SeventyFiveMoveRule,
+    // claimed when the same position occurred three times
+    ThreefoldRepetition,
+    // the same position occurred five times
+    FivefoldRepetition,
+    // the time of the loser ran out
+    Timeout(PieceColor),
+    // the time of a player ran out but the winner has no pieces to checkmate with, a draw
+    TimeoutVsInsufficientMaterial,
+    // the loser gave up, the winner is given
+    Resignation(PieceColor),
+    // the players agreed to a draw
+    DrawAgreement,
+    // called off before both players made a move, nobody wins and it isn't a draw
+    Aborted,
+}
+
+impl GameEnd {
+    // None for a draw or an aborted game
+    pub fn winner(self) -> Option<PieceColor> {
+        match self {
+            GameEnd::Checkmate(winner) | GameEnd::Timeout(winner) | GameEnd::Resignation(winner) => Some(winner),
+            _ => None
+        }
+    }
+}
+
+// returns the side the king castles to if the move is castling
+pub(crate) fn castling_side(piece: Piece, mv: Move) -> Option<CastlingSide> {
+    if piece.piece_type != PieceType::King || mv.from.rank() != mv.to.rank() {
+        return None;
+    }
+
+    match mv.to.file() as i8 - mv.from.file() as i8 {
+        2 => Some(CastlingSide::Kingside),
+        -2 => Some(CastlingSide::Queenside),
+        _ => None
+    }
+}
+
+// what make_move has to remember to take the move back
+#[derive(Clone, Copy)]
+struct Undo {
+    mv: Move,
+    captured: Option<Piece>,
+    castling_rights: CastlingRights,
+    en_passant: Option<Square>,
+    halfmove_clock: u32,
+}
+
+#[derive(Clone)]
+pub struct Position {
+    pub(crate) board: Board,
+    pub(crate) side_to_move: PieceColor,
+    pub(crate) castling_rights: CastlingRights,
+    // square a pawn passed with a double push, it can be taken there on the next move
+    pub(crate) en_passant: Option<Square>,
+    // half moves since the last capture or pawn move
+    halfmove_clock: u32,
+    // starts at 1 and goes up after every move of black
+    fullmove_number: u32,
+    undo_stack: Vec<Undo>,
+    // zobrist hashes of all positions of the game, the last one is the current position
+    hashes: Vec<u64>,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position::new()
+    }
+}
+
+impl Position {
+    // the standard starting position
+    pub fn new() -> Position {
+        let back_rank = [PieceType::Rook, PieceType::Knight, PieceType::Bishop, PieceType::Queen, PieceType::King, PieceType::Bishop, PieceType::Knight, PieceType::Rook];
+
+        let mut board = Board::empty();
+        for (file, piece_type) in back_rank.iter().enumerate() {
+            let file = file as u8;
+            board.set(Square::new(0, file), Some(Piece::new(PieceColor::White, *piece_type)));
+            board.set(Square::new(1, file), Some(Piece::new(PieceColor::White, PieceType::Pawn)));
+            board.set(Square::new(6, file), Some(Piece::new(PieceColor::Black, PieceType::Pawn)));
+            board.set(Square::new(7, file), Some(Piece::new(PieceColor::Black, *piece_type)));
+        }
+
+        Position::from_parts(board, PieceColor::White, CastlingRights::ALL, None, 0, 1)
+    }
+
+    // a position without moves played before it, like one loaded from a fen
+    pub(crate) fn from_parts(board: Board, side_to_move: PieceColor, castling_rights: CastlingRights, en_passant: Option<Square>, halfmove_clock: u32, fullmove_number: u32) -> Position {
+        let mut position = Position {
+            board,
+            side_to_move,
+            castling_rights,
+            en_passant,
+            halfmove_clock,
+            fullmove_number,
+            undo_stack: Vec::new(),
+            hashes: Vec::new(),
+        };
+        position.hashes.push(position.compute_hash());
+        position
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.board.piece_at(square)
+    }
+
+    // all pieces with the square they are on
+    pub fn pieces(&self) -> impl Iterator<Item = (Square, Piece)> + '_ {
+        self.board.occupied().filter_map(move |square| self.piece_at(square).map(|piece| (square, piece)))
+    }
+
+    // squares of the pieces of one type and color
+    pub fn bitboard(&self, color: PieceColor, piece_type: PieceType) -> Bitboard {
+        self.board.pieces(color, piece_type)
+    }
+
+    // squares of all pieces of the color
+    pub fn color_bitboard(&self, color: PieceColor) -> Bitboard {
+        self.board.color(color)
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.board.occupied()
+    }
+
+    pub fn side_to_move(&self) -> PieceColor {
+        self.side_to_move
+    }
+
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.castling_rights
+    }
+
+    pub fn en_passant(&self) -> Option<Square> {
+        self.en_passant
+    }
+
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    // the move that led to this position, None at the start
+    pub fn last_move(&self) -> Option<Move> {
+        self.undo_stack.last().map(|undo| undo.mv)
+    }
+
+    pub fn is_square_attacked(&self, square: Square, by: PieceColor) -> bool {
+        self.board.is_attacked(square, by)
+    }
+
+    pub fn king_square(&self, color: PieceColor) -> Option<Square> {
+        self.board.king_square(color)
+    }
+
+    // returns true if the king of the player to move is attacked
+    pub fn is_check(&self) -> bool {
+        match self.board.king_square(self.side_to_move) {
+            Some(king_square) => self.board.is_attacked(king_square, self.side_to_move.opposite()),
+            None => false
+        }
+    }
+
+    // returns true if the player to move can play the move without leaving their king in check
+    pub fn is_legal(&self, mv: Move) -> bool {
+        self.is_pseudo_legal(mv) && self.is_king_safe_after(mv)
+    }
+
+    // tries the move on a copy of the board and returns true if the king of the player to move isn't attacked
+    pub(crate) fn is_king_safe_after(&self, mv: Move) -> bool {
+        let mut board = self.board;
+        board.apply(mv, self.en_passant);
+        match board.king_square(self.side_to_move) {
+            Some(king_square) => !board.is_attacked(king_square, self.side_to_move.opposite()),
+            None => true
+        }
+    }
+
+    // returns true if the piece can reach the square, no matter if its king is in check afterwards
+    fn is_pseudo_legal(&self, mv: Move) -> bool {
+        let piece = match self.piece_at(mv.from) {
+            Some(piece) if piece.color == self.side_to_move => piece,
+            _ => return false
+        };
+
+        // a piece can't take a piece of the same color
+        let target = self.piece_at(mv.to);
+        if mv.from == mv.to || target.is_some_and(|target| target.color == piece.color) {
+            return false;
+        }
+
+        // pawns reaching the last rank have to promote to a queen, rook, bishop or knight
+        let reaches_last_rank = piece.piece_type == PieceType::Pawn && mv.to.rank() == piece.color.opposite().home_rank();
+        match mv.promotion {
+            None if reaches_last_rank => return false,
+            Some(PieceType::Queen) | Some(PieceType::Rook) | Some(PieceType::Bishop) | Some(PieceType::Knight) if !reaches_last_rank => return false,
+            Some(PieceType::King) | Some(PieceType::Pawn) => return false,
+            _ => {}
+        }
+
+        match piece.piece_type {
+            PieceType::King => {
+                match castling_side(piece, mv) {
+                    Some(side) => self.is_castling_possible(side),
+                    None => self.board.attacks(mv.from, piece, mv.to)
+                }
+            }
+            PieceType::Pawn => {
+                let direction = piece.color.pawn_direction();
+                let rank_diff = mv.to.rank() as i8 - mv.from.rank() as i8;
+
+                if mv.from.file() == mv.to.file() {
+                    // pushes go to empty squares, two squares only from the starting rank
+                    let start_rank = piece.color.home_rank() as i8 + direction;
+                    target.is_none() && (rank_diff == direction
+                        || (rank_diff == 2 * direction && mv.from.rank() as i8 == start_rank && self.board.is_path_empty(mv.from, mv.to)))
+                } else {
+                    // captures go diagonally forward, onto a piece or the en passant square
+                    self.board.attacks(mv.from, piece, mv.to) && (target.is_some() || Some(mv.to) == self.en_passant)
+                }
+            }
+            _ => self.board.attacks(mv.from, piece, mv.to)
+        }
+    }
+
+    // king and rook must be on their squares with the castling right left, the squares between them
+    // must be empty and the king can't castle out of, through or into check
+    pub(crate) fn is_castling_possible(&self, side: CastlingSide) -> bool {
+        let color = self.side_to_move;
+        let king_square = Square::new(color.home_rank(), 4);
+        let rook_square = Square::new(color.home_rank(), side.rook_file());
+
+        if !self.castling_rights.has(color, side)
+        || self.piece_at(king_square) != Some(Piece::new(color, PieceType::King))
+        || self.piece_at(rook_square) != Some(Piece::new(color, PieceType::Rook))
+        || !self.board.is_path_empty(king_square, rook_square) {
+            return false;
+        }
+
+        let direction = if side == CastlingSide::Kingside { 1 } else { -1 };
+        (0..3).filter_map(|i| king_square.offset(0, i * direction))
+            .all(|square| !self.board.is_attacked(square, color.opposite()))
+    }
+
+    // plays a legal move, check it with is_legal first
+    pub fn make_move(&mut self, mv: Move) {
+        let piece = self.piece_at(mv.from).expect("no piece to move");
+        let castling_rights = self.castling_rights;
+        let en_passant = self.en_passant;
+        let halfmove_clock = self.halfmove_clock;
+
+        let captured = self.board.apply(mv, self.en_passant);
+
+        // moving the king loses both castling rights, moving a rook or losing it the one on its side
+        if piece.piece_type == PieceType::King {
+            self.castling_rights.remove(piece.color, CastlingSide::Kingside);
+            self.castling_rights.remove(piece.color, CastlingSide::Queenside);
+        }
+        for color in [PieceColor::White, PieceColor::Black].iter() {
+            for side in [CastlingSide::Kingside, CastlingSide::Queenside].iter() {
+                let rook_square = Square::new(color.home_rank(), side.rook_file());
+                if mv.from == rook_square || mv.to == rook_square {
+                    self.castling_rights.remove(*color, *side);
+                }
+            }
+        }
+
+        // a double push lets the pawn be taken on the square it passed
+        let rank_diff = mv.to.rank() as i8 - mv.from.rank() as i8;
+        self.en_passant = if piece.piece_type == PieceType::Pawn && rank_diff.abs() == 2 {
+            mv.from.offset(rank_diff / 2, 0)
+        } else {
+            None
+        };
+
+        if piece.piece_type == PieceType::Pawn || captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+
+        if self.side_to_move == PieceColor::Black {
+            self.fullmove_number += 1;
+        }
+        self.side_to_move = self.side_to_move.opposite();
+
+        self.undo_stack.push(Undo { mv, captured, castling_rights, en_passant, halfmove_clock });
+        self.hashes.push(self.compute_hash());
+    }
+
+    // takes back the last move and returns it, None if no move was played
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let undo = self.undo_stack.pop()?;
+        self.hashes.pop();
+
+        self.side_to_move = self.side_to_move.opposite();
+        if self.side_to_move == PieceColor::Black {
+            self.fullmove_number -= 1;
+        }
+
+        let mv = undo.mv;
+        let mut piece = self.piece_at(mv.to).expect("no piece to move back");
+        if mv.promotion.is_some() {
+            piece.piece_type = PieceType::Pawn;
+        }
+        self.board.set(mv.from, Some(piece));
+        self.board.set(mv.to, None);
+
+        // put the taken piece back, a pawn taken en passant stands next to the moving one
+        if piece.piece_type == PieceType::Pawn && mv.from.file() != mv.to.file() && Some(mv.to) == undo.en_passant {
+            self.board.set(Square::new(mv.from.rank(), mv.to.file()), undo.captured);
+        } else {
+            self.board.set(mv.to, undo.captured);
+        }
+
+        if let Some(side) = castling_side(piece, mv) {
+            let rook_from = Square::new(mv.from.rank(), side.rook_file());
+            let rook_to = Square::new(mv.from.rank(), side.rook_to_file());
+            self.board.set(rook_from, self.piece_at(rook_to));
+            self.board.set(rook_to, None);
+        }
+
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        Some(mv)
+    }
+
+    // returns true if the player to move has any legal move
+    pub fn has_legal_moves(&self) -> bool {
+        !self.legal_moves().is_empty()
+    }
+
+    // returns true if neither side can checkmate: king against king, king and bishop or knight against king
+    // or only bishops on squares of the same color
+    pub fn is_insufficient_material(&self) -> bool {
+        let minor_pieces: Vec<(Square, Piece)> = self.pieces().filter(|(_, piece)| piece.piece_type != PieceType::King).collect();
+
+        if minor_pieces.iter().any(|(_, piece)| piece.piece_type != PieceType::Bishop && piece.piece_type != PieceType::Knight) {
+            return false;
+        }
+
+        let square_color = |square: Square| (square.rank() + square.file()) % 2;
+        minor_pieces.len() <= 1 || minor_pieces.iter().all(|(square, piece)| piece.piece_type == PieceType::Bishop
+            && square_color(*square) == square_color(minor_pieces[0].0))
+    }
+
+    // returns false if the player can't checkmate with any series of legal moves: they only have their king,
+    // or the position is a draw by insufficient material anyway
+    pub fn can_checkmate(&self, color: PieceColor) -> bool {
+        self.pieces().any(|(_, piece)| piece.color == color && piece.piece_type != PieceType::King) && !self.is_insufficient_material()
+    }
+
+    // how the game ends when the time of the given player runs out in this position
+    pub fn timeout(&self, flagged: PieceColor) -> GameEnd {
+        if self.can_checkmate(flagged.opposite()) {
+            GameEnd::Timeout(flagged.opposite())
+        } else {
+            GameEnd::TimeoutVsInsufficientMaterial
+        }
+    }
+
+    // zobrist hash of the pieces, the side to move, the castling rights and the en passant square
+    // if a pawn can take there, equal positions by the repetition rules have the same hash
+    pub fn hash(&self) -> u64 {
+        *self.hashes.last().expect("no hash of the current position")
+    }
+
+    // the same position without the moves before it, a cheap copy to try a move on
+    pub(crate) fn without_history(&self) -> Position {
+        Position {
+            board: self.board,
+            side_to_move: self.side_to_move,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            undo_stack: Vec::new(),
+            hashes: vec![self.hash()],
+        }
+    }
+
+    // how often the current position occurred in the game, it can't have occurred before the last capture or pawn move
+    pub fn repetitions(&self) -> usize {
+        let current = self.hash();
+        self.hashes.iter().rev().take(self.halfmove_clock as usize + 1).step_by(2).filter(|hash| **hash == current).count()
+    }
+
+    // returns how the game ended if the rules end it, a checkmate wins over the automatic draws
+    pub fn game_end(&self) -> Option<GameEnd> {
+        if !self.has_legal_moves() {
+            Some(if self.is_check() {
+                GameEnd::Checkmate(self.side_to_move.opposite())
+            } else {
+                GameEnd::Stalemate
+            })
+        } else if self.is_insufficient_material() {
+            Some(GameEnd::InsufficientMaterial)
+        } else if self.repetitions() >= 5 {
+            Some(GameEnd::FivefoldRepetition)
+        } else if self.halfmove_clock >= 150 {
+            Some(GameEnd::SeventyFiveMoveRule)
+        } else {
+            None
+        }
+    }
+
+    // returns the draw the player to move can claim, if any
+    pub fn claimable_draw(&self) -> Option<GameEnd> {
+        if self.repetitions() >= 3 {
+            Some(GameEnd::ThreefoldRepetition)
+        } else if self.halfmove_clock >= 100 {
+            Some(GameEnd::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    // the board keeps the hash of the pieces up to date, the rest is added on top
+    fn compute_hash(&self) -> u64 {
+        let mut hash = self.board.hash() ^ ZOBRIST.castling[self.castling_rights.index()];
+        if self.side_to_move == PieceColor::Black {
+            hash ^= ZOBRIST.black_to_move;
+        }
+        if let Some(en_passant) = self.en_passant.filter(|_| self.is_en_passant_possible()) {
+            hash ^= ZOBRIST.en_passant[en_passant.file() as usize];
+        }
+        hash
+    }
+
+    // returns true if a pawn of the player to move can take en passant
+    fn is_en_passant_possible(&self) -> bool {
+        let en_passant = match self.en_passant {
+            Some(en_passant) => en_passant,
+            None => return false
+        };
+
+        let pawn_rank = -self.side_to_move.pawn_direction();
+        [-1, 1].iter().filter_map(|file| en_passant.offset(pawn_rank, *file))
+            .any(|from| self.piece_at(from) == Some(Piece::new(self.side_to_move, PieceType::Pawn)) && self.is_legal(Move::new(from, en_passant)))
+    }
+}
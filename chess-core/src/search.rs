@@ -0,0 +1,527 @@
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{eval::*, moves::*, position::*, tt::*, types::*};
+
+// score of a checkmate on the board, a mate further away scores one less for every ply
+pub const MATE_SCORE: i32 = 30_000;
+const INFINITY: i32 = 32_000;
+
+// the search never goes deeper than this, quiescence search included
+pub const MAX_PLY: usize = 64;
+
+// the clock and the stop flag are only looked at every this many nodes
+const CHECK_INTERVAL: u64 = 1024;
+
+// when the search stops, it finishes at the depth, time or number of nodes reached first
+#[derive(Clone, Default, Debug)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub time: Option<Duration>,
+    pub nodes: Option<u64>,
+    // only these moves are searched at the root, every legal move if it is empty
+    pub moves: Vec<Move>,
+}
+
+// size of the transposition table in megabytes if the options don't say otherwise
+pub const DEFAULT_HASH_SIZE: usize = 16;
+
+// how the computer plays, besides how long it searches
+#[derive(Clone, Copy, Debug)]
+pub struct SearchOptions {
+    pub style: Style,
+    // every move at the root gets a random bonus up to this many centipawns, so weaker levels make mistakes
+    pub noise: i32,
+    // size of the transposition table in megabytes
+    pub hash_size: usize,
+    // number of best moves searched with their own variation, like the uci option MultiPV
+    pub multi_pv: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            style: Style::Balanced,
+            noise: 0,
+            hash_size: DEFAULT_HASH_SIZE,
+            multi_pv: 1,
+        }
+    }
+}
+
+// a strength setting of the computer, the elo is a rough guess against people
+#[derive(Clone, Copy, Debug)]
+pub struct Skill {
+    pub name: &'static str,
+    pub elo: u32,
+    pub depth: u32,
+    pub time: Duration,
+    pub noise: i32,
+}
+
+impl Skill {
+    pub fn limits(&self) -> SearchLimits {
+        SearchLimits { depth: Some(self.depth), time: Some(self.time), ..Default::default() }
+    }
+}
+
+// from the weakest to the strongest
+pub const SKILLS: [Skill; 6] = [
+    Skill { name: "beginner", elo: 800, depth: 1, time: Duration::from_millis(100), noise: 400 },
+    Skill { name: "novice", elo: 1000, depth: 2, time: Duration::from_millis(250), noise: 200 },
+    Skill { name: "club", elo: 1300, depth: 3, time: Duration::from_millis(500), noise: 80 },
+    Skill { name: "intermediate", elo: 1600, depth: 4, time: Duration::from_secs(1), noise: 30 },
+    Skill { name: "advanced", elo: 1900, depth: 6, time: Duration::from_secs(2), noise: 10 },
+    Skill { name: "master", elo: 2100, depth: MAX_PLY as u32, time: Duration::from_secs(5), noise: 0 },
+];
+
+// moves to the mate if the score is one, negative when the player to move gets mated
+fn mate_in(score: i32) -> Option<i32> {
+    let plies = MATE_SCORE - score.abs();
+    if plies > MAX_PLY as i32 {
+        return None;
+    }
+    let moves = (plies + 1) / 2;
+    Some(if score > 0 { moves } else { -moves })
+}
+
+// one of the best moves with its score and variation
+#[derive(Clone, Default, Debug)]
+pub struct SearchLine {
+    pub score: i32,
+    pub pv: Vec<Move>,
+}
+
+impl SearchLine {
+    pub fn mate_in(&self) -> Option<i32> {
+        mate_in(self.score)
+    }
+}
+
+// best move and score of the deepest finished iteration
+#[derive(Clone, Default, Debug)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    // centipawns from the view of the player to move
+    pub score: i32,
+    pub depth: u32,
+    // deepest ply reached, quiescence search included
+    pub seldepth: u32,
+    pub nodes: u64,
+    // how full the transposition table is in permille
+    pub hashfull: u32,
+    pub elapsed: Duration,
+    // principal variation, starts with the best move
+    pub pv: Vec<Move>,
+    // the variations of the best moves from the best down, as many as the multi_pv option asks for
+    pub lines: Vec<SearchLine>,
+}
+
+impl SearchResult {
+    pub fn mate_in(&self) -> Option<i32> {
+        mate_in(self.score)
+    }
+}
+
+// negamax alpha-beta search with iterative deepening, keeps its move ordering tables between the iterations
+pub struct Searcher {
+    options: SearchOptions,
+    stop: Arc<AtomicBool>,
+    // kept between searches, a new search starts with what the last one found
+    tt: TranspositionTable,
+    // two quiet moves per ply that caused a beta cutoff
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    // how often a quiet move from a square to a square caused a cutoff, weighted by depth
+    history: [[i32; 64]; 64],
+    // principal variation of the last iteration, searched first in the next one
+    pv: Vec<Move>,
+    // random bonus of every move at the root, the same in every iteration
+    root_noise: Vec<(Move, i32)>,
+    // moves searched at the root, without the best moves of the earlier variations of the iteration
+    root_moves: Vec<Move>,
+    nodes: u64,
+    seldepth: usize,
+    start: Instant,
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    stopped: bool,
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Searcher::new()
+    }
+}
+
+impl Searcher {
+    pub fn new() -> Searcher {
+        Searcher::with_options(SearchOptions::default())
+    }
+
+    pub fn with_options(options: SearchOptions) -> Searcher {
+        Searcher {
+            options,
+            stop: Arc::new(AtomicBool::new(false)),
+            tt: TranspositionTable::new(options.hash_size),
+            killers: [[None; 2]; MAX_PLY],
+            history: [[0; 64]; 64],
+            pv: Vec::new(),
+            root_noise: Vec::new(),
+            root_moves: Vec::new(),
+            nodes: 0,
+            seldepth: 0,
+            start: Instant::now(),
+            deadline: None,
+            node_limit: None,
+            stopped: false,
+        }
+    }
+
+    pub fn options(&self) -> SearchOptions {
+        self.options
+    }
+
+    // a new hash size starts with an empty transposition table
+    pub fn set_options(&mut self, options: SearchOptions) {
+        if options.hash_size != self.options.hash_size {
+            self.tt.resize(options.hash_size);
+        }
+        self.options = options;
+    }
+
+    // forgets the results of earlier searches, for a new game
+    pub fn clear_hash(&mut self) {
+        self.tt.clear();
+    }
+
+    // how full the transposition table is in permille
+    pub fn hashfull(&self) -> u32 {
+        self.tt.hashfull()
+    }
+
+    // setting the flag from another thread stops the search, it returns the result of the last finished iteration,
+    // the flag is cleared when the search ends so a stop set before the search starts stops it right away
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    pub fn search(&mut self, position: &Position, limits: SearchLimits) -> SearchResult {
+        self.search_with(position, limits, |_| {})
+    }
+
+    // like search, calls on_iteration with the result of every finished depth
+    pub fn search_with(&mut self, position: &Position, limits: SearchLimits, on_iteration: impl FnMut(&SearchResult)) -> SearchResult {
+        let result = self.iterative_deepening(position, limits, on_iteration);
+        self.stop.store(false, Ordering::Relaxed);
+        result
+    }
+
+    fn iterative_deepening(&mut self, position: &Position, limits: SearchLimits, mut on_iteration: impl FnMut(&SearchResult)) -> SearchResult {
+        let mut position = position.clone();
+        self.killers = [[None; 2]; MAX_PLY];
+        self.history = [[0; 64]; 64];
+        self.pv.clear();
+        self.nodes = 0;
+        self.seldepth = 0;
+        self.start = Instant::now();
+        self.deadline = limits.time.map(|time| self.start + time);
+        self.node_limit = limits.nodes;
+        self.stopped = false;
+        self.tt.new_search();
+        self.root_noise = self.random_noise(&position);
+
+        let max_depth = limits.depth.unwrap_or(MAX_PLY as u32).clamp(1, MAX_PLY as u32 / 2);
+        let searched_moves: Vec<Move> = position.legal_moves().into_iter().filter(|mv| limits.moves.is_empty() || limits.moves.contains(mv)).collect();
+        let mut result = SearchResult::default();
+
+        for depth in 1..=max_depth {
+            // every further variation is searched without the best moves of the variations before it
+            let mut lines = Vec::new();
+            self.root_moves = searched_moves.clone();
+            while lines.len() < self.options.multi_pv.max(1) && !self.root_moves.is_empty() {
+                let mut pv = Vec::new();
+                let score = self.negamax(&mut position, depth, 0, -INFINITY, INFINITY, &mut pv);
+                // an unfinished iteration can miss the best move
+                if self.stopped || pv.is_empty() {
+                    break;
+                }
+
+                self.complete_pv(&mut position, &mut pv, depth);
+                self.root_moves.retain(|mv| *mv != pv[0]);
+                // the noise only decides which move gets played
+                lines.push(SearchLine { score: score - self.noise(pv[0]), pv });
+            }
+            if self.stopped || lines.is_empty() {
+                break;
+            }
+
+            self.pv = lines[0].pv.clone();
+            result = SearchResult {
+                best_move: lines[0].pv.first().copied(),
+                score: lines[0].score,
+                depth,
+                seldepth: self.seldepth as u32,
+                nodes: self.nodes,
+                hashfull: self.tt.hashfull(),
+                elapsed: self.start.elapsed(),
+                pv: lines[0].pv.clone(),
+                lines,
+            };
+            on_iteration(&result);
+
+            // no time for another iteration, or the game ends anyway
+            if result.best_move.is_none() || result.mate_in().is_some_and(|moves| moves.unsigned_abs() * 2 <= depth) {
+                break;
+            }
+            if let Some(time) = limits.time {
+                if self.start.elapsed() * 2 > time {
+                    break;
+                }
+            }
+        }
+
+        // the first iteration was stopped, any legal move is better than none
+        if result.best_move.is_none() {
+            result.best_move = searched_moves.first().copied();
+            result.nodes = self.nodes;
+            result.elapsed = self.start.elapsed();
+        }
+        result
+    }
+
+    // cutoffs by the transposition table leave out the end of the variation, the table still has the moves
+    fn complete_pv(&self, position: &mut Position, pv: &mut Vec<Move>, depth: u32) {
+        for mv in pv.iter() {
+            position.make_move(*mv);
+        }
+        let mut played = pv.len();
+        while pv.len() < depth as usize {
+            match self.tt.probe(position.hash()).and_then(|entry| entry.best_move).filter(|mv| position.is_legal(*mv)) {
+                Some(mv) => {
+                    position.make_move(mv);
+                    pv.push(mv);
+                    played += 1;
+                }
+                None => break
+            }
+        }
+        for _ in 0..played {
+            position.unmake_move();
+        }
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if !self.stopped && self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            self.stopped = self.stop.load(Ordering::Relaxed)
+                || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                || self.node_limit.is_some_and(|limit| self.nodes >= limit);
+        }
+        self.stopped
+    }
+
+    fn negamax(&mut self, position: &mut Position, depth: u32, ply: usize, mut alpha: i32, beta: i32, pv: &mut Vec<Move>) -> i32 {
+        pv.clear();
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        if self.should_stop() {
+            return 0;
+        }
+
+        // repeating a position once is enough to call it a draw inside the search
+        if ply > 0 && (position.halfmove_clock() >= 100 || position.repetitions() >= 2 || position.is_insufficient_material()) {
+            return 0;
+        }
+        if ply >= MAX_PLY {
+            return position.evaluate_style(self.options.style);
+        }
+
+        // a check doesn't count as a ply, so the search sees through it
+        let in_check = position.is_check();
+        let depth = if in_check { depth + 1 } else { depth };
+        if depth == 0 {
+            return self.quiescence(position, ply, alpha, beta);
+        }
+
+        // an earlier search of the same position at least as deep can answer right away, except at the root
+        let hash = position.hash();
+        let entry = self.tt.probe(hash);
+        if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth as u32 >= depth) {
+            let score = entry.score(ply);
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower if score >= beta => return score,
+                Bound::Upper if score <= alpha => return score,
+                _ => {}
+            }
+        }
+
+        let mut moves = position.legal_moves();
+        if moves.is_empty() {
+            return if in_check { -MATE_SCORE + ply as i32 } else { 0 };
+        }
+        if ply == 0 {
+            moves.retain(|mv| self.root_moves.contains(mv));
+        }
+        self.order_moves(position, &mut moves, ply, entry.and_then(|entry| entry.best_move));
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        let mut best_noise = 0;
+        let mut child_pv = Vec::new();
+        for mv in moves {
+            // the window moves with the noise, so a move only needs to beat alpha together with its bonus
+            let noise = if ply == 0 { self.noise(mv) } else { 0 };
+            position.make_move(mv);
+            let score = -self.negamax(position, depth - 1, ply + 1, noise - beta, noise - alpha, &mut child_pv) + noise;
+            position.unmake_move();
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+                best_noise = noise;
+                if score > alpha {
+                    alpha = score;
+                    pv.clear();
+                    pv.push(mv);
+                    pv.extend_from_slice(&child_pv);
+                }
+                if score >= beta {
+                    if !mv.is_capture() && mv.promotion.is_none() {
+                        self.store_killer(mv, ply);
+                        self.history[mv.from.index()][mv.to.index()] += (depth * depth) as i32;
+                    }
+                    break;
+                }
+            }
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        // the noise only picks the root move, the table gets the score of the best move without it, which the position is worth
+        // at least, and a fail low stays an upper bound because the noise only adds to the scores
+        let (tt_score, bound) = match bound {
+            Bound::Upper => (best_score, bound),
+            _ if best_noise > 0 => (best_score - best_noise, Bound::Lower),
+            _ => (best_score, bound)
+        };
+        // the root is only stored when every move was searched
+        if ply > 0 || self.root_moves.len() == position.legal_moves().len() {
+            self.tt.store(hash, depth, tt_score, bound, best_move, ply);
+        }
+        best_score
+    }
+
+    // searches captures until the position is quiet, so the evaluation doesn't stop in the middle of an exchange
+    fn quiescence(&mut self, position: &mut Position, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        if self.should_stop() {
+            return 0;
+        }
+        if ply >= MAX_PLY {
+            return position.evaluate_style(self.options.style);
+        }
+
+        // the player can stand pat unless in check, then every evasion has to be searched
+        let in_check = position.is_check();
+        let mut best_score = -INFINITY;
+        if !in_check {
+            best_score = position.evaluate_style(self.options.style);
+            if best_score >= beta {
+                return best_score;
+            }
+            alpha = alpha.max(best_score);
+        }
+
+        let mut moves = position.legal_moves();
+        if moves.is_empty() && in_check {
+            return -MATE_SCORE + ply as i32;
+        }
+        if !in_check {
+            moves.retain(|mv| mv.is_capture() || mv.promotion == Some(PieceType::Queen));
+        }
+        self.order_moves(position, &mut moves, ply, None);
+
+        for mv in moves {
+            position.make_move(mv);
+            let score = -self.quiescence(position, ply + 1, -beta, -alpha);
+            position.unmake_move();
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                alpha = alpha.max(score);
+                if score >= beta {
+                    break;
+                }
+            }
+        }
+        best_score
+    }
+
+    fn noise(&self, mv: Move) -> i32 {
+        self.root_noise.iter().find(|(noisy_move, _)| *noisy_move == mv).map_or(0, |(_, noise)| *noise)
+    }
+
+    // a bonus between 0 and the noise of the options for every legal move, from a xorshift generator seeded by the clock
+    fn random_noise(&self, position: &Position) -> Vec<(Move, i32)> {
+        if self.options.noise <= 0 {
+            return Vec::new();
+        }
+
+        let mut state = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64) | 1;
+        position.legal_moves().into_iter().map(|mv| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (mv, (state % (self.options.noise as u64 + 1)) as i32)
+        }).collect()
+    }
+
+    fn store_killer(&mut self, mv: Move, ply: usize) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+    }
+
+    // the move from the transposition table and of the last principal variation first, then captures by most valuable
+    // victim and least valuable attacker, queen promotions, killer moves and the quiet moves by their history
+    fn order_moves(&self, position: &Position, moves: &mut [Move], ply: usize, tt_move: Option<Move>) {
+        let pv_move = self.pv.get(ply).copied();
+        let killers = self.killers[ply];
+
+        moves.sort_by_cached_key(|mv| {
+            let score = if Some(*mv) == tt_move {
+                2_000_000
+            } else if Some(*mv) == pv_move {
+                1_000_000
+            } else if mv.is_capture() {
+                let victim = position.piece_at(mv.to).map_or(PieceType::Pawn, |piece| piece.piece_type);
+                let attacker = position.piece_at(mv.from).map_or(PieceType::Pawn, |piece| piece.piece_type);
+                100_000 + piece_value(victim) * 10 - piece_value(attacker)
+            } else if mv.promotion == Some(PieceType::Queen) {
+                90_000
+            } else if Some(*mv) == killers[0] {
+                80_000
+            } else if Some(*mv) == killers[1] {
+                79_000
+            } else {
+                self.history[mv.from.index()][mv.to.index()].min(70_000)
+            };
+            std::cmp::Reverse(score)
+        });
+    }
+}
@@ -0,0 +1,107 @@
+use std::mem::size_of;
+
+use crate::{moves::*, search::*};
+
+// how the stored score relates to the real score of the position
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Bound {
+    Exact,
+    // the real score is at least the stored one, the search cut off
+    Lower,
+    // the real score is at most the stored one, no move reached alpha
+    Upper,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Entry {
+    hash: u64,
+    pub(crate) best_move: Option<Move>,
+    score: i32,
+    pub(crate) depth: u8,
+    pub(crate) bound: Bound,
+    // search the entry was stored in, entries of older searches get replaced first
+    generation: u8,
+}
+
+impl Entry {
+    // mate scores are stored from the entry's position and count from the root again when read
+    pub(crate) fn score(&self, ply: usize) -> i32 {
+        if self.score > MATE_SCORE - MAX_PLY as i32 {
+            self.score - ply as i32
+        } else if self.score < -MATE_SCORE + MAX_PLY as i32 {
+            self.score + ply as i32
+        } else {
+            self.score
+        }
+    }
+}
+
+// results of earlier searches by zobrist hash, a deeper result keeps its slot over a shallower one
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+    generation: u8,
+}
+
+impl TranspositionTable {
+    // the table takes up to the given size in megabytes, at least one entry
+    pub fn new(megabytes: usize) -> TranspositionTable {
+        let len = (megabytes * 1024 * 1024 / size_of::<Option<Entry>>()).max(1);
+        TranspositionTable {
+            entries: vec![None; len],
+            generation: 0,
+        }
+    }
+
+    pub fn resize(&mut self, megabytes: usize) {
+        *self = TranspositionTable::new(megabytes);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+        self.generation = 0;
+    }
+
+    // how full the table is in permille, counted from the first thousand entries
+    pub fn hashfull(&self) -> u32 {
+        let sample = self.entries.len().min(1000);
+        let used = self.entries[..sample].iter().filter(|entry| entry.is_some_and(|entry| entry.generation == self.generation)).count();
+        (used * 1000 / sample) as u32
+    }
+
+    // called at the start of every search
+    pub(crate) fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    fn index(&self, hash: u64) -> usize {
+        (hash % self.entries.len() as u64) as usize
+    }
+
+    pub(crate) fn probe(&self, hash: u64) -> Option<Entry> {
+        self.entries[self.index(hash)].filter(|entry| entry.hash == hash)
+    }
+
+    pub(crate) fn store(&mut self, hash: u64, depth: u32, score: i32, bound: Bound, best_move: Option<Move>, ply: usize) {
+        let index = self.index(hash);
+        let generation = self.generation;
+
+        // depth-preferred, but anything beats an entry of an older search
+        if let Some(old) = self.entries[index] {
+            if old.generation == generation && old.depth as u32 > depth {
+                return;
+            }
+        }
+
+        let score = if score > MATE_SCORE - MAX_PLY as i32 {
+            score + ply as i32
+        } else if score < -MATE_SCORE + MAX_PLY as i32 {
+            score - ply as i32
+        } else {
+            score
+        };
+        // an entry without a move keeps the move of the same position
+        let best_move = best_move.or_else(|| self.entries[index].filter(|old| old.hash == hash).and_then(|old| old.best_move));
+
+        self.entries[index] = Some(Entry { hash, best_move, score, depth: depth.min(u8::MAX as u32) as u8, bound, generation });
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PieceColor {
+    White,
+    Black,
+}
+
+impl PieceColor {
+    pub fn opposite(&self) -> PieceColor {
+        match self {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White
+        }
+    }
+
+    // rank of the king and rooks at the start
+    pub fn home_rank(&self) -> u8 {
+        match self {
+            PieceColor::White => 0,
+            PieceColor::Black => 7
+        }
+    }
+
+    // direction the pawns move in
+    pub fn pawn_direction(&self) -> i8 {
+        match self {
+            PieceColor::White => 1,
+            PieceColor::Black => -1
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PieceType {
+    King,
+    Queen,
+    Bishop,
+    Knight,
+    Rook,
+    Pawn,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Piece {
+    pub color: PieceColor,
+    pub piece_type: PieceType,
+}
+
+impl Piece {
+    pub fn new(color: PieceColor, piece_type: PieceType) -> Piece {
+        Piece { color, piece_type }
+    }
+}
+
+// one of the 64 squares, a1 is 0, b1 is 1 and h8 is 63
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Square(u8);
+
+impl Square {
+    // rank and file count from 0, the bevy app calls them x and y
+    pub fn new(rank: u8, file: u8) -> Square {
+        assert!(rank < 8 && file < 8, "square out of the board");
+        Square(rank * 8 + file)
+    }
+
+    pub fn from_index(index: usize) -> Square {
+        assert!(index < 64, "square out of the board");
+        Square(index as u8)
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn rank(self) -> u8 {
+        self.0 / 8
+    }
+
+    pub fn file(self) -> u8 {
+        self.0 % 8
+    }
+
+    // returns the square the given number of ranks and files away, None if it is off the board
+    pub fn offset(self, rank_delta: i8, file_delta: i8) -> Option<Square> {
+        let rank = self.rank() as i8 + rank_delta;
+        let file = self.file() as i8 + file_delta;
+
+        if (0..8).contains(&rank) && (0..8).contains(&file) {
+            Some(Square::new(rank as u8, file as u8))
+        } else {
+            None
+        }
+    }
+
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..64).map(Square)
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.file()) as char, self.rank() + 1)
+    }
+}
@@ -0,0 +1,350 @@
+use std::{error::Error, fmt};
+use std::time::Duration;
+
+use crate::{fen::*, moves::*, notation::*, position::*, types::*};
+
+// score of an engine, from the view of the player to move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Score {
+    Centipawns(i32),
+    // moves to the mate, negative if the player to move gets mated
+    Mate(i32),
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Score::Centipawns(centipawns) => write!(f, "cp {}", centipawns),
+            Score::Mate(moves) => write!(f, "mate {}", moves),
+        }
+    }
+}
+
+// time kept back on every move for the gui and the pipes
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+// moves the remaining clock time is shared between when the gui doesn't say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+// the parameters of a `go` command, a search without any of them goes on until `stop`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GoParams {
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    // moves until the next time control
+    pub movestogo: Option<u32>,
+    pub movetime: Option<Duration>,
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub mate: Option<u32>,
+    pub infinite: bool,
+    pub ponder: bool,
+    // only search these moves, in uci notation
+    pub searchmoves: Vec<String>,
+}
+
+impl GoParams {
+    // reads the words after `go`, unknown words are skipped
+    pub fn parse(text: &str) -> GoParams {
+        let mut params = GoParams::default();
+        let mut words = text.split_whitespace().peekable();
+        let millis = |word: Option<&str>| word.and_then(|word| word.parse::<i64>().ok()).map(|millis| Duration::from_millis(millis.max(0) as u64));
+
+        while let Some(word) = words.next() {
+            match word {
+                "wtime" => params.wtime = millis(words.next()),
+                "btime" => params.btime = millis(words.next()),
+                "winc" => params.winc = millis(words.next()),
+                "binc" => params.binc = millis(words.next()),
+                "movestogo" => params.movestogo = words.next().and_then(|word| word.parse().ok()),
+                "movetime" => params.movetime = millis(words.next()),
+                "depth" => params.depth = words.next().and_then(|word| word.parse().ok()),
+                "nodes" => params.nodes = words.next().and_then(|word| word.parse().ok()),
+                "mate" => params.mate = words.next().and_then(|word| word.parse().ok()),
+                "infinite" => params.infinite = true,
+                "ponder" => params.ponder = true,
+                "searchmoves" => {
+                    // the moves go on until the next keyword
+                    while let Some(mv) = words.next_if(|word| word.len() >= 4 && word.chars().nth(1).is_some_and(|c| c.is_ascii_digit())) {
+                        params.searchmoves.push(mv.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        params
+    }
+
+    // how long the player of the color should think, the movetime or a share of the time left on their clock,
+    // None without either
+    pub fn time_for_move(&self, color: PieceColor) -> Option<Duration> {
+        if let Some(movetime) = self.movetime {
+            return Some(movetime.saturating_sub(MOVE_OVERHEAD).max(Duration::from_millis(1)));
+        }
+
+        let (remaining, increment) = match color {
+            PieceColor::White => (self.wtime?, self.winc.unwrap_or_default()),
+            PieceColor::Black => (self.btime?, self.binc.unwrap_or_default())
+        };
+        let moves_to_go = self.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let share = remaining / moves_to_go + increment * 3 / 4;
+        // never more than the clock has left
+        Some(share.min(remaining.saturating_sub(MOVE_OVERHEAD)).max(Duration::from_millis(1)))
+    }
+
+    // the command to send, starting with `go`
+    pub fn to_command(&self) -> String {
+        let mut command = "go".to_string();
+        if !self.searchmoves.is_empty() {
+            command += &format!(" searchmoves {}", self.searchmoves.join(" "));
+        }
+        if self.ponder {
+            command += " ponder";
+        }
+        let times = [("wtime", self.wtime), ("btime", self.btime), ("winc", self.winc), ("binc", self.binc), ("movetime", self.movetime)];
+        for (name, time) in times.iter() {
+            if let Some(time) = time {
+                command += &format!(" {} {}", name, time.as_millis());
+            }
+        }
+        if let Some(movestogo) = self.movestogo {
+            command += &format!(" movestogo {}", movestogo);
+        }
+        if let Some(depth) = self.depth {
+            command += &format!(" depth {}", depth);
+        }
+        if let Some(nodes) = self.nodes {
+            command += &format!(" nodes {}", nodes);
+        }
+        if let Some(mate) = self.mate {
+            command += &format!(" mate {}", mate);
+        }
+        if self.infinite {
+            command += " infinite";
+        }
+        command
+    }
+}
+
+// what an engine tells about its search in an `info` line, everything it left out is None
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EngineInfo {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub multipv: Option<u32>,
+    pub score: Option<Score>,
+    // the score is only a bound, the real one is at least or at most it
+    pub lowerbound: bool,
+    pub upperbound: bool,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub time: Option<Duration>,
+    pub hashfull: Option<u32>,
+    pub currmove: Option<String>,
+    // moves in uci notation
+    pub pv: Vec<String>,
+    pub string: Option<String>,
+}
+
+impl EngineInfo {
+    // reads the words after `info`, unknown words are skipped
+    pub fn parse(text: &str) -> EngineInfo {
+        let mut info = EngineInfo::default();
+        let mut words = text.split_whitespace();
+
+        while let Some(word) = words.next() {
+            match word {
+                "depth" => info.depth = words.next().and_then(|word| word.parse().ok()),
+                "seldepth" => info.seldepth = words.next().and_then(|word| word.parse().ok()),
+                "multipv" => info.multipv = words.next().and_then(|word| word.parse().ok()),
+                "score" => {
+                    info.score = match (words.next(), words.next().and_then(|word| word.parse().ok())) {
+                        (Some("cp"), Some(value)) => Some(Score::Centipawns(value)),
+                        (Some("mate"), Some(value)) => Some(Score::Mate(value)),
+                        _ => None
+                    };
+                }
+                "lowerbound" => info.lowerbound = true,
+                "upperbound" => info.upperbound = true,
+                "nodes" => info.nodes = words.next().and_then(|word| word.parse().ok()),
+                "nps" => info.nps = words.next().and_then(|word| word.parse().ok()),
+                "time" => info.time = words.next().and_then(|word| word.parse().ok()).map(Duration::from_millis),
+                "hashfull" => info.hashfull = words.next().and_then(|word| word.parse().ok()),
+                "currmove" => info.currmove = words.next().map(str::to_string),
+                // the variation and the string go on until the end of the line
+                "pv" => {
+                    info.pv = words.by_ref().map(str::to_string).collect();
+                }
+                "string" => {
+                    info.string = Some(words.by_ref().collect::<Vec<_>>().join(" "));
+                }
+                _ => {}
+            }
+        }
+        info
+    }
+
+    // the legal moves of the variation played from the position, it stops at the first move that isn't legal
+    pub fn pv_moves(&self, position: &Position) -> Vec<Move> {
+        let mut position = position.clone();
+        let mut moves = Vec::new();
+        for uci in self.pv.iter() {
+            match position.parse_uci(uci) {
+                Ok(mv) => {
+                    position.make_move(mv);
+                    moves.push(mv);
+                }
+                Err(_) => break
+            }
+        }
+        moves
+    }
+}
+
+// the line to send, starting with `info`
+impl fmt::Display for EngineInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "info")?;
+        if let Some(depth) = self.depth {
+            write!(f, " depth {}", depth)?;
+        }
+        if let Some(seldepth) = self.seldepth {
+            write!(f, " seldepth {}", seldepth)?;
+        }
+        if let Some(multipv) = self.multipv {
+            write!(f, " multipv {}", multipv)?;
+        }
+        if let Some(score) = self.score {
+            write!(f, " score {}", score)?;
+            if self.lowerbound {
+                write!(f, " lowerbound")?;
+            }
+            if self.upperbound {
+                write!(f, " upperbound")?;
+            }
+        }
+        if let Some(nodes) = self.nodes {
+            write!(f, " nodes {}", nodes)?;
+        }
+        if let Some(nps) = self.nps {
+            write!(f, " nps {}", nps)?;
+        }
+        if let Some(hashfull) = self.hashfull {
+            write!(f, " hashfull {}", hashfull)?;
+        }
+        if let Some(time) = self.time {
+            write!(f, " time {}", time.as_millis())?;
+        }
+        if let Some(currmove) = &self.currmove {
+            write!(f, " currmove {}", currmove)?;
+        }
+        if !self.pv.is_empty() {
+            write!(f, " pv {}", self.pv.join(" "))?;
+        }
+        if let Some(string) = &self.string {
+            write!(f, " string {}", string)?;
+        }
+        Ok(())
+    }
+}
+
+// the `position` command for the start position and the moves played from it
+pub fn position_command(start: &Position, moves: &[Move]) -> String {
+    let mut command = if start.to_fen() == Position::new().to_fen() {
+        "position startpos".to_string()
+    } else {
+        format!("position fen {}", start.to_fen())
+    };
+    if !moves.is_empty() {
+        command += " moves";
+        for mv in moves {
+            command += &format!(" {}", mv);
+        }
+    }
+    command
+}
+
+// a command from the gui to the engine
+#[derive(Clone)]
+pub enum UciCommand {
+    Uci,
+    Debug(bool),
+    IsReady,
+    SetOption { name: String, value: Option<String> },
+    UciNewGame,
+    // the position after the moves were played, it remembers them for repetitions
+    Position(Position),
+    Go(GoParams),
+    Stop,
+    PonderHit,
+    Quit,
+}
+
+// what is wrong with a command from the gui
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum UciCommandError {
+    // engines ignore commands they don't know
+    Unknown(String),
+    // the command lacks a part, like `position` without `startpos` or `fen`
+    Incomplete(String),
+    Fen(FenError),
+    Move(NotationError),
+}
+
+impl fmt::Display for UciCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UciCommandError::Unknown(command) => write!(f, "unknown command '{}'", command),
+            UciCommandError::Incomplete(command) => write!(f, "'{}' is missing a part", command),
+            UciCommandError::Fen(error) => write!(f, "invalid fen: {}", error),
+            UciCommandError::Move(error) => write!(f, "invalid move: {}", error),
+        }
+    }
+}
+
+impl Error for UciCommandError {}
+
+impl UciCommand {
+    pub fn parse(line: &str) -> Result<UciCommand, UciCommandError> {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let incomplete = || UciCommandError::Incomplete(line.to_string());
+
+        match command {
+            "uci" => Ok(UciCommand::Uci),
+            "debug" => Ok(UciCommand::Debug(rest.trim() != "off")),
+            "isready" => Ok(UciCommand::IsReady),
+            // "setoption name Clear Hash" or "setoption name Hash value 64", names and values can contain spaces
+            "setoption" => {
+                let rest = rest.trim().strip_prefix("name ").ok_or_else(incomplete)?;
+                match rest.split_once(" value ") {
+                    Some((name, value)) => Ok(UciCommand::SetOption { name: name.trim().to_string(), value: Some(value.trim().to_string()) }),
+                    None => Ok(UciCommand::SetOption { name: rest.trim().to_string(), value: None })
+                }
+            }
+            "ucinewgame" => Ok(UciCommand::UciNewGame),
+            // "position startpos moves e2e4 e7e5" or "position fen <fen> moves e2e4"
+            "position" => {
+                let (setup, moves) = rest.split_once("moves").unwrap_or((rest, ""));
+                let setup = setup.trim();
+                let mut position = if setup == "startpos" {
+                    Position::new()
+                } else {
+                    let fen = setup.strip_prefix("fen").ok_or_else(incomplete)?;
+                    Position::from_fen(fen.trim()).map_err(UciCommandError::Fen)?
+                };
+                for uci in moves.split_whitespace() {
+                    let mv = position.parse_uci(uci).map_err(UciCommandError::Move)?;
+                    position.make_move(mv);
+                }
+                Ok(UciCommand::Position(position))
+            }
+            "go" => Ok(UciCommand::Go(GoParams::parse(rest))),
+            "stop" => Ok(UciCommand::Stop),
+            "ponderhit" => Ok(UciCommand::PonderHit),
+            "quit" => Ok(UciCommand::Quit),
+            _ => Err(UciCommandError::Unknown(command.to_string()))
+        }
+    }
+}
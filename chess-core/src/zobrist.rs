@@ -0,0 +1,78 @@
+use crate::types::*;
+
+// random numbers xored together to hash a position, the same in every run so hashes can be kept
+pub(crate) struct ZobristKeys {
+    // indexed by color, piece type and square
+    pieces: [[[u64; 64]; 6]; 2],
+    pub(crate) black_to_move: u64,
+    // indexed by the bits of the castling rights
+    pub(crate) castling: [u64; 16],
+    // indexed by the file of the en passant square
+    pub(crate) en_passant: [u64; 8],
+}
+
+pub(crate) static ZOBRIST: ZobristKeys = ZobristKeys::generate();
+
+impl ZobristKeys {
+    // fills the tables from a splitmix64 generator with a fixed seed
+    const fn generate() -> ZobristKeys {
+        let mut keys = ZobristKeys {
+            pieces: [[[0; 64]; 6]; 2],
+            black_to_move: 0,
+            castling: [0; 16],
+            en_passant: [0; 8],
+        };
+        let mut state = 0x2545_f491_4f6c_dd1d;
+
+        let mut color = 0;
+        while color < 2 {
+            let mut piece_type = 0;
+            while piece_type < 6 {
+                let mut square = 0;
+                while square < 64 {
+                    let (next_state, key) = splitmix64(state);
+                    state = next_state;
+                    keys.pieces[color][piece_type][square] = key;
+                    square += 1;
+                }
+                piece_type += 1;
+            }
+            color += 1;
+        }
+
+        let (next_state, key) = splitmix64(state);
+        state = next_state;
+        keys.black_to_move = key;
+
+        // no castling rights don't change the hash
+        let mut index = 1;
+        while index < 16 {
+            let (next_state, key) = splitmix64(state);
+            state = next_state;
+            keys.castling[index] = key;
+            index += 1;
+        }
+
+        let mut file = 0;
+        while file < 8 {
+            let (next_state, key) = splitmix64(state);
+            state = next_state;
+            keys.en_passant[file] = key;
+            file += 1;
+        }
+        keys
+    }
+
+    pub(crate) fn piece(&self, piece: Piece, square: Square) -> u64 {
+        self.pieces[piece.color as usize][piece.piece_type as usize][square.index()]
+    }
+}
+
+// returns the next state and the random number
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (state, z ^ (z >> 31))
+}
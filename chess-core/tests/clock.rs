@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use chess_core::{Clock, GameEnd, PieceColor, Position, TimeControl, TimeControlError, TimingMethod};
+
+fn secs(seconds: u64) -> Duration {
+    Duration::from_secs(seconds)
+}
+
+// lets the player to move think for the given seconds and press the clock
+fn play(clock: &mut Clock, seconds: u64) {
+    clock.tick(secs(seconds));
+    clock.press();
+}
+
+#[test]
+fn parse() {
+    assert_eq!(TimeControl::parse("5+3"), Ok(TimeControl::fischer(secs(300), secs(3))));
+    assert_eq!(TimeControl::parse("90"), Ok(TimeControl::sudden_death(secs(5400))));
+    assert_eq!(TimeControl::parse("5d2"), Ok(TimeControl::simple_delay(secs(300), secs(2))));
+    assert_eq!(TimeControl::parse("5b2"), Ok(TimeControl::bronstein(secs(300), secs(2))));
+    assert_eq!(TimeControl::parse("0.5+0.5"), Ok(TimeControl::fischer(secs(30), Duration::from_millis(500))));
+
+    let classical = TimeControl::parse("40/90+30:30+30").unwrap();
+    assert_eq!(classical.stages.len(), 2);
+    assert_eq!(classical.stages[0].moves, Some(40));
+    assert_eq!(classical.stages[0].time, secs(5400));
+    assert_eq!(classical.stages[1].moves, None);
+    assert_eq!(classical.stages[1].increment, secs(30));
+    assert_eq!(classical.stages[1].method, TimingMethod::Increment);
+
+    for text in ["5+3", "90", "5d2", "5b2", "40/90+30:30+30", "40/120:20/60:30"].iter() {
+        assert_eq!(TimeControl::parse(text).unwrap().to_string(), *text);
+    }
+    assert_eq!(classical.to_pgn(), "40/5400+30:1800+30");
+
+    assert_eq!(TimeControl::parse(" "), Err(TimeControlError::Empty));
+    assert_eq!(TimeControl::parse("5x3"), Err(TimeControlError::InvalidStage("5x3".to_string())));
+    assert_eq!(TimeControl::parse("0+3"), Err(TimeControlError::InvalidStage("0+3".to_string())));
+    assert_eq!(TimeControl::parse("0/90"), Err(TimeControlError::InvalidStage("0/90".to_string())));
+    assert_eq!(TimeControl::parse("90:30"), Err(TimeControlError::StageAfterRestOfGame("30".to_string())));
+}
+
+#[test]
+fn sudden_death() {
+    let mut clock = Clock::new(TimeControl::sudden_death(secs(60)), PieceColor::White);
+    play(&mut clock, 10);
+    assert_eq!(clock.remaining(PieceColor::White), secs(50));
+    assert_eq!(clock.turn(), PieceColor::Black);
+
+    clock.tick(secs(59));
+    assert_eq!(clock.flagged(), None);
+    clock.tick(secs(2));
+    assert_eq!(clock.remaining(PieceColor::Black), secs(0));
+    assert_eq!(clock.flagged(), Some(PieceColor::Black));
+
+    // a flagged clock stands still
+    clock.press();
+    clock.tick(secs(5));
+    assert_eq!(clock.turn(), PieceColor::Black);
+    assert_eq!(clock.remaining(PieceColor::White), secs(50));
+}
+
+#[test]
+fn increment_and_delays() {
+    let mut fischer = Clock::new(TimeControl::fischer(secs(60), secs(5)), PieceColor::White);
+    play(&mut fischer, 2);
+    assert_eq!(fischer.remaining(PieceColor::White), secs(63));
+
+    // bronstein gives back the time used, up to the delay
+    let mut bronstein = Clock::new(TimeControl::bronstein(secs(60), secs(5)), PieceColor::White);
+    play(&mut bronstein, 2);
+    assert_eq!(bronstein.remaining(PieceColor::White), secs(60));
+    play(&mut bronstein, 8);
+    assert_eq!(bronstein.remaining(PieceColor::Black), secs(57));
+
+    // a simple delay runs down before the clock does
+    let mut delay = Clock::new(TimeControl::simple_delay(secs(60), secs(5)), PieceColor::White);
+    assert_eq!(delay.delay_left(), secs(5));
+    delay.tick(secs(3));
+    assert_eq!(delay.delay_left(), secs(2));
+    assert_eq!(delay.remaining(PieceColor::White), secs(60));
+    delay.tick(secs(4));
+    assert_eq!(delay.remaining(PieceColor::White), secs(58));
+    delay.press();
+    assert_eq!(delay.remaining(PieceColor::White), secs(58));
+    assert_eq!(delay.delay_left(), secs(5));
+}
+
+#[test]
+fn stages() {
+    let mut clock = Clock::new(TimeControl::parse("2/10+1:5").unwrap(), PieceColor::White);
+    assert_eq!(clock.moves_to_go(PieceColor::White), Some(2));
+    assert_eq!(clock.go_params().movestogo, Some(2));
+    assert_eq!(clock.go_params().winc, Some(secs(1)));
+
+    play(&mut clock, 60);
+    play(&mut clock, 60);
+    assert_eq!(clock.remaining(PieceColor::White), secs(541));
+    assert_eq!(clock.moves_to_go(PieceColor::White), Some(1));
+
+    // the second move finishes the first stage, the time of the next one is added
+    play(&mut clock, 60);
+    assert_eq!(clock.remaining(PieceColor::White), secs(482 + 300));
+    assert_eq!(clock.moves_to_go(PieceColor::White), None);
+    assert_eq!(clock.stage(PieceColor::White).increment, secs(0));
+
+    // a last stage with a move count repeats
+    let repeating = TimeControl::parse("40/120:20/60").unwrap();
+    assert_eq!(repeating.stage_number(39), 0);
+    assert_eq!(repeating.stage_number(40), 1);
+    assert_eq!(repeating.stage_number(60), 2);
+    assert_eq!(repeating.moves_to_go(65), Some(15));
+    assert_eq!(repeating.stage(2).time, secs(3600));
+}
+
+#[test]
+fn timeout() {
+    // the winner needs a piece that can give mate
+    let position = Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+    assert_eq!(position.timeout(PieceColor::Black), GameEnd::Timeout(PieceColor::White));
+    assert_eq!(position.timeout(PieceColor::White), GameEnd::TimeoutVsInsufficientMaterial);
+    assert_eq!(GameEnd::Timeout(PieceColor::White).winner(), Some(PieceColor::White));
+    assert_eq!(GameEnd::TimeoutVsInsufficientMaterial.winner(), None);
+
+    let position = Position::from_fen("4k3/8/8/8/8/8/4N3/4K3 b - - 0 1").unwrap();
+    assert!(!position.can_checkmate(PieceColor::White));
+    let position = Position::from_fen("4k3/4p3/8/8/8/8/4N3/4K3 b - - 0 1").unwrap();
+    assert!(position.can_checkmate(PieceColor::White));
+}
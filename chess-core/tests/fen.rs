@@ -0,0 +1,59 @@
+use chess_core::{FenError, PieceColor, Position, Square};
+
+const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[test]
+fn start_position() {
+    assert_eq!(Position::new().to_fen(), START);
+    assert_eq!(Position::from_fen(START).unwrap().to_fen(), START);
+}
+
+#[test]
+fn round_trip() {
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 37 90",
+        "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1",
+    ];
+    for fen in fens.iter() {
+        assert_eq!(Position::from_fen(fen).unwrap().to_fen(), *fen);
+    }
+}
+
+#[test]
+fn counters_are_optional() {
+    let position = Position::from_fen("8/8/8/8/8/8/8/K6k w - -").unwrap();
+    assert_eq!(position.halfmove_clock(), 0);
+    assert_eq!(position.fullmove_number(), 1);
+}
+
+#[test]
+fn moves_update_the_fen() {
+    let mut position = Position::new();
+    let e2e4 = position.legal_moves().into_iter().find(|mv| mv.from == Square::new(1, 4) && mv.to == Square::new(3, 4)).unwrap();
+    position.make_move(e2e4);
+    assert_eq!(position.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+}
+
+#[test]
+fn errors() {
+    let cases = [
+        ("8/8/8/8/8/8/8/K6k w -", FenError::FieldCount(3)),
+        ("8/8/8/8/8/8/K6k w - - 0 1", FenError::RankCount(7)),
+        ("8/8/8/8/8/8/8/K5k w - - 0 1", FenError::RankLength { rank: 1, squares: 7 }),
+        ("8/8/8/8/8/8/8/K6kk w - - 0 1", FenError::RankLength { rank: 1, squares: 9 }),
+        ("8/8/8/8/8/8/8/K6x w - - 0 1", FenError::InvalidPiece('x')),
+        ("8/8/8/8/8/8/8/K6k x - - 0 1", FenError::InvalidSideToMove("x".to_string())),
+        ("8/8/8/8/8/8/8/K6k w K - 0 1", FenError::InvalidCastlingRights("K".to_string())),
+        ("8/8/8/8/8/8/8/K6k w - e3 0 1", FenError::InvalidEnPassant("e3".to_string())),
+        ("8/8/8/8/8/8/8/K6k w - - x 1", FenError::InvalidHalfmoveClock("x".to_string())),
+        ("8/8/8/8/8/8/8/K6k w - - 0 0", FenError::InvalidFullmoveNumber("0".to_string())),
+        ("8/8/8/8/8/8/8/K7 w - - 0 1", FenError::KingCount { color: PieceColor::Black, count: 0 }),
+        ("P7/8/8/8/8/8/8/K6k w - - 0 1", FenError::PawnOnBackRank(Square::new(7, 0))),
+        ("8/8/8/8/8/8/8/K5Rk w - - 0 1", FenError::OpponentInCheck),
+    ];
+    for (fen, error) in cases.iter() {
+        assert_eq!(Position::from_fen(fen).err().as_ref(), Some(error), "{}", fen);
+    }
+}
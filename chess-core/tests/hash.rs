@@ -0,0 +1,113 @@
+use chess_core::{OpeningBook, Position, SearchLimits, SearchOptions, Searcher};
+
+fn play(position: &mut Position, moves: &str) {
+    for uci in moves.split_whitespace() {
+        let mv = position.parse_uci(uci).unwrap();
+        position.make_move(mv);
+    }
+}
+
+// walks the move tree and compares the incremental hash with the hash of the same position read from its fen
+fn check_hashes(position: &mut Position, depth: u32) {
+    assert_eq!(position.hash(), Position::from_fen(&position.to_fen()).unwrap().hash(), "{}", position.to_fen());
+    if depth == 0 {
+        return;
+    }
+
+    let hash = position.hash();
+    for mv in position.legal_moves() {
+        position.make_move(mv);
+        check_hashes(position, depth - 1);
+        position.unmake_move();
+        assert_eq!(position.hash(), hash);
+    }
+}
+
+#[test]
+fn incremental() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    ];
+    for fen in fens.iter() {
+        check_hashes(&mut Position::from_fen(fen).unwrap(), 2);
+    }
+}
+
+#[test]
+fn transpositions() {
+    let mut first = Position::new();
+    play(&mut first, "e2e4 e7e5 g1f3");
+    let mut second = Position::new();
+    play(&mut second, "g1f3 e7e5 e2e4");
+    assert_eq!(first.hash(), second.hash());
+
+    // the side to move, the castling rights and a possible en passant capture make a different position
+    let mut third = Position::new();
+    play(&mut third, "e2e4 e7e5 g1f3 b8c6");
+    assert_ne!(first.hash(), third.hash());
+
+    let with_castling = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    let without_castling = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w Kkq - 0 1").unwrap();
+    assert_ne!(with_castling.hash(), without_castling.hash());
+
+    let en_passant = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+    let no_en_passant = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 2").unwrap();
+    assert_ne!(en_passant.hash(), no_en_passant.hash());
+
+    // an en passant square no pawn can take on doesn't count
+    let useless = Position::from_fen("4k3/8/8/3p4/8/8/8/4K3 w - d6 0 2").unwrap();
+    let none = Position::from_fen("4k3/8/8/3p4/8/8/8/4K3 w - - 0 2").unwrap();
+    assert_eq!(useless.hash(), none.hash());
+}
+
+#[test]
+fn repetitions() {
+    let mut position = Position::new();
+    assert_eq!(position.repetitions(), 1);
+    play(&mut position, "g1f3 g8f6 f3g1 f6g8");
+    assert_eq!(position.repetitions(), 2);
+    play(&mut position, "g1f3 g8f6 f3g1 f6g8");
+    assert_eq!(position.repetitions(), 3);
+    assert!(position.claimable_draw().is_some());
+
+    position.unmake_move();
+    assert_eq!(position.repetitions(), 2);
+}
+
+#[test]
+fn transposition_table() {
+    // the table survives between searches and a second search of the same position is faster
+    let position = Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+    let limits = SearchLimits { depth: Some(4), ..Default::default() };
+    let mut searcher = Searcher::with_options(SearchOptions { hash_size: 1, ..Default::default() });
+
+    let first = searcher.search(&position, limits.clone());
+    assert!(searcher.hashfull() > 0);
+    let second = searcher.search(&position, limits);
+    assert!(second.nodes < first.nodes);
+    assert_eq!(second.pv.len(), 4);
+
+    searcher.clear_hash();
+    assert_eq!(searcher.hashfull(), 0);
+}
+
+#[test]
+fn opening_book() {
+    let book = OpeningBook::builtin();
+    let mut position = Position::new();
+    let moves: Vec<String> = book.moves(&position).iter().map(|mv| mv.to_string()).collect();
+    assert!(moves.contains(&"e2e4".to_string()) && moves.contains(&"d2d4".to_string()));
+
+    // transpositions find the book moves too
+    play(&mut position, "g1f3 g8f6 c2c4 e7e6 b1c3");
+    assert!(!book.moves(&position).is_empty());
+
+    let mut unknown = Position::new();
+    play(&mut unknown, "a2a4 h7h5");
+    assert!(book.moves(&unknown).is_empty());
+    assert!(book.pick(&unknown, 7).is_none());
+    assert!(book.pick(&Position::new(), 7).is_some());
+}
@@ -0,0 +1,18 @@
+use std::collections::HashSet;
+
+use chess_core::{Move, PieceType, Position, Square};
+
+#[test]
+fn hand_built_moves() {
+    // a move built from its squares is the generated one, whatever flags the generator gave it
+    let position = Position::from_fen("r3k3/1P6/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+    let moves = position.legal_moves();
+    let castling = Move::new(Square::new(0, 4), Square::new(0, 6));
+    assert!(moves.iter().any(|mv| *mv == castling && mv.is_castling()));
+    let capture = Move::with_promotion(Square::new(6, 1), Square::new(7, 0), PieceType::Knight);
+    assert!(moves.contains(&capture));
+    assert_ne!(capture, Move::with_promotion(Square::new(6, 1), Square::new(7, 0), PieceType::Queen));
+
+    let generated: HashSet<Move> = moves.into_iter().collect();
+    assert!(generated.contains(&castling) && generated.contains(&capture));
+}
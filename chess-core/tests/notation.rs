@@ -0,0 +1,105 @@
+use chess_core::{Move, NotationError, PieceType, Position, Square};
+
+fn square(name: &str) -> Square {
+    let bytes = name.as_bytes();
+    Square::new(bytes[1] - b'1', bytes[0] - b'a')
+}
+
+// the legal move between the squares, with a queen promotion if the pawn promotes
+fn find_move(position: &Position, from: &str, to: &str) -> Move {
+    position.legal_moves().into_iter()
+        .find(|mv| mv.from == square(from) && mv.to == square(to) && mv.promotion.is_none_or(|promotion| promotion == PieceType::Queen))
+        .unwrap_or_else(|| panic!("{}{} isn't legal", from, to))
+}
+
+fn san(fen: &str, from: &str, to: &str) -> String {
+    let position = Position::from_fen(fen).unwrap();
+    position.san(find_move(&position, from, to))
+}
+
+#[test]
+fn san_of_moves() {
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    assert_eq!(san(start, "e2", "e4"), "e4");
+    assert_eq!(san(start, "g1", "f3"), "Nf3");
+
+    // captures, en passant and promotions
+    assert_eq!(san("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2", "e4", "d5"), "exd5");
+    assert_eq!(san("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", "e5", "f6"), "exf6");
+    assert_eq!(san("8/4P3/8/8/8/8/8/K6k w - - 0 1", "e7", "e8"), "e8=Q");
+
+    // castling, check and checkmate
+    assert_eq!(san("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1", "g1"), "O-O");
+    assert_eq!(san("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1", "c1"), "O-O-O");
+    assert_eq!(san("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1", "a1", "a8"), "Ra8+");
+    assert_eq!(san("6k1/5ppp/8/8/8/8/8/R3K3 w Q - 0 1", "a1", "a8"), "Ra8#");
+}
+
+#[test]
+fn san_disambiguation() {
+    // knights on b1 and f3 both reach d2
+    assert_eq!(san("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1", "b1", "d2"), "Nbd2");
+    // rooks on a1 and a5 both reach a3
+    assert_eq!(san("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1", "a3"), "R1a3");
+    // queens on a1, c1 and a3 all reach b2
+    assert_eq!(san("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "a1", "b2"), "Qa1b2");
+}
+
+#[test]
+fn parse_san() {
+    let position = Position::from_fen("4k3/8/8/R7/8/5N2/4P3/RN2K3 w Q - 0 1").unwrap();
+    assert_eq!(position.parse_san("Nbd2"), Ok(find_move(&position, "b1", "d2")));
+    assert_eq!(position.parse_san("R1a3"), Ok(find_move(&position, "a1", "a3")));
+    assert_eq!(position.parse_san("e4!?"), Ok(find_move(&position, "e2", "e4")));
+    assert_eq!(position.parse_san("O-O-O"), Err(NotationError::Illegal("O-O-O".to_string())));
+    assert_eq!(position.parse_san("Nd2"), Err(NotationError::Ambiguous("Nd2".to_string())));
+    assert_eq!(position.parse_san("Ra3"), Err(NotationError::Ambiguous("Ra3".to_string())));
+    assert_eq!(position.parse_san("Zz9"), Err(NotationError::Invalid("Zz9".to_string())));
+
+    let promotion = Position::from_fen("8/4P3/8/8/8/8/8/K6k w - - 0 1").unwrap();
+    assert_eq!(promotion.parse_san("e8=N").unwrap().promotion, Some(PieceType::Knight));
+    assert_eq!(promotion.parse_san("e8Q+").unwrap().promotion, Some(PieceType::Queen));
+}
+
+#[test]
+fn long_algebraic_and_uci() {
+    let position = Position::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K1NR w KQkq d6 0 1").unwrap();
+    let cases = [
+        ("g1", "f3", "Ng1-f3", "g1f3"),
+        ("e5", "d6", "e5xd6", "e5d6"),
+        ("b7", "a8", "b7xa8=Q+", "b7a8q"),
+        ("e1", "c1", "O-O-O", "e1c1"),
+    ];
+    for (from, to, lan, uci) in cases.iter() {
+        let mv = find_move(&position, from, to);
+        assert_eq!(position.long_algebraic(mv), *lan);
+        assert_eq!(mv.to_string(), *uci);
+        assert_eq!(position.parse_long_algebraic(lan), Ok(mv));
+        assert_eq!(position.parse_uci(uci), Ok(mv));
+    }
+
+    assert_eq!(position.parse_uci("b7b8n").unwrap().promotion, Some(PieceType::Knight));
+    assert_eq!(position.parse_long_algebraic("Bf1-b5"), Err(NotationError::Illegal("Bf1-b5".to_string())));
+    assert_eq!(position.parse_uci("e2e4"), Err(NotationError::Illegal("e2e4".to_string())));
+    assert_eq!(position.parse_uci("e2"), Err(NotationError::Invalid("e2".to_string())));
+    assert_eq!(position.parse_uci("b7b8k"), Err(NotationError::Invalid("b7b8k".to_string())));
+}
+
+// every legal move has to come back from its san, long algebraic and uci notation
+#[test]
+fn round_trip() {
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1",
+    ];
+    for fen in fens.iter() {
+        let position = Position::from_fen(fen).unwrap();
+        for mv in position.legal_moves() {
+            assert_eq!(position.parse_san(&position.san(mv)), Ok(mv), "{} in {}", position.san(mv), fen);
+            assert_eq!(position.parse_long_algebraic(&position.long_algebraic(mv)), Ok(mv), "{} in {}", position.long_algebraic(mv), fen);
+            assert_eq!(position.parse_uci(&mv.to_string()), Ok(mv), "{} in {}", mv, fen);
+        }
+    }
+}
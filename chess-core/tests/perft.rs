@@ -0,0 +1,77 @@
+use chess_core::{PieceColor, Position, Square};
+
+// node counts from https://www.chessprogramming.org/Perft_Results
+fn assert_perft(fen: &str, expected: &[u64]) {
+    let mut position = Position::from_fen(fen).unwrap();
+    for (depth, nodes) in expected.iter().enumerate() {
+        assert_eq!(position.perft(depth as u32 + 1), *nodes, "perft({}) of {}", depth + 1, fen);
+    }
+}
+
+#[test]
+fn start_position() {
+    let mut position = Position::new();
+    assert_eq!(position.perft(1), 20);
+    assert_eq!(position.perft(2), 400);
+    assert_eq!(position.perft(3), 8_902);
+    assert_eq!(position.perft(4), 197_281);
+}
+
+#[test]
+fn kiwipete() {
+    assert_perft("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2_039, 97_862]);
+}
+
+#[test]
+fn position_3() {
+    assert_perft("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2_812, 43_238, 674_624]);
+}
+
+#[test]
+fn position_4() {
+    assert_perft("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9_467, 422_333]);
+}
+
+#[test]
+fn position_4_mirrored() {
+    assert_perft("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1", &[6, 264, 9_467, 422_333]);
+}
+
+#[test]
+fn position_5() {
+    assert_perft("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1_486, 62_379]);
+}
+
+#[test]
+fn position_6() {
+    assert_perft("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2_079, 89_890]);
+}
+
+// too slow for every test run, run with `cargo test --release -- --ignored`
+#[test]
+#[ignore]
+fn deep() {
+    let mut position = Position::new();
+    assert_eq!(position.perft(5), 4_865_609);
+    assert_perft("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2_039, 97_862, 4_085_603]);
+    assert_perft("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1_486, 62_379, 2_103_487]);
+    assert_perft("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2_079, 89_890, 3_894_594]);
+}
+
+#[test]
+fn moves_from_square() {
+    // the pawn on b7 pushes or takes the rook with four promotions each
+    let position = Position::from_fen("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    let pawn_moves = position.legal_moves_from(Square::new(6, 1));
+    assert_eq!(pawn_moves.len(), 8);
+    assert_eq!(pawn_moves.iter().filter(|mv| mv.is_capture()).count(), 4);
+    assert!(pawn_moves.iter().all(|mv| mv.promotion.is_some()));
+    assert!(position.legal_moves_from(Square::new(3, 3)).is_empty());
+
+    // in check from the rook only the king can move, off the first rank
+    let position = Position::from_fen("r3k3/1P6/8/8/8/8/8/4K2r w - - 0 1").unwrap();
+    assert!(position.is_check());
+    assert_eq!(position.king_square(PieceColor::White), Some(Square::new(0, 4)));
+    assert!(position.legal_moves_from(Square::new(6, 1)).is_empty());
+    assert!(position.legal_moves_from(Square::new(0, 4)).iter().all(|mv| mv.to.rank() == 1));
+}
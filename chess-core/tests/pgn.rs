@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use chess_core::{clock_comment, read_pgn, FenError, GameEnd, Move, NotationError, PgnErrorKind, PgnGame, PgnResult, PieceColor, PieceType, Position, Square};
+
+fn square(name: &str) -> Square {
+    let bytes = name.as_bytes();
+    Square::new(bytes[1] - b'1', bytes[0] - b'a')
+}
+
+// the legal move between the squares, with a queen promotion if the pawn promotes
+fn find_move(position: &Position, from: &str, to: &str) -> Move {
+    position.legal_moves().into_iter()
+        .find(|mv| mv.from == square(from) && mv.to == square(to) && mv.promotion.is_none_or(|promotion| promotion == PieceType::Queen))
+        .unwrap_or_else(|| panic!("{}{} isn't legal", from, to))
+}
+
+#[test]
+fn export() {
+    let mut game = PgnGame::new(Position::new());
+    game.set_tag("White", "alice");
+    let mut position = Position::new();
+    for (i, (from, to)) in [("e2", "e4"), ("e7", "e5"), ("f1", "c4"), ("b8", "c6"), ("d1", "h5"), ("g8", "f6"), ("h5", "f7")].iter().enumerate() {
+        let mv = find_move(&position, from, to);
+        position.make_move(mv);
+        let comment = if i == 2 { Some(clock_comment(Duration::from_secs(299))) } else { None };
+        game.push(mv, comment);
+    }
+    game.result = PgnResult::from_game_end(position.game_end());
+
+    assert_eq!(game.to_pgn(), "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n[White \"alice\"]\n[Black \"?\"]\n[Result \"1-0\"]\n\n\
+        1. e4 e5 2. Bc4 {[%clk 0:04:59]} 2... Nc6 3. Qh5 Nf6 4. Qxf7# 1-0\n");
+}
+
+#[test]
+fn export_from_fen() {
+    let start = Position::from_fen("4k3/8/8/8/8/8/8/R3K3 b Q - 0 40").unwrap();
+    let mut game = PgnGame::new(start.clone());
+    game.push(find_move(&start, "e8", "d8"), None);
+
+    let pgn = game.to_pgn();
+    assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b Q - 0 40\"]\n"));
+    assert!(pgn.ends_with("\n40... Kd8 *\n"));
+}
+
+#[test]
+fn terminations() {
+    let results = [
+        (GameEnd::Resignation(PieceColor::Black), PgnResult::BlackWins, "normal"),
+        (GameEnd::DrawAgreement, PgnResult::Draw, "normal"),
+        (GameEnd::Timeout(PieceColor::White), PgnResult::WhiteWins, "time forfeit"),
+        (GameEnd::TimeoutVsInsufficientMaterial, PgnResult::Draw, "time forfeit"),
+        (GameEnd::Aborted, PgnResult::Unfinished, "abandoned"),
+    ];
+    for (game_end, result, termination) in results.iter() {
+        assert_eq!(PgnResult::from_game_end(Some(*game_end)), *result);
+        assert_eq!(game_end.termination(), *termination);
+    }
+}
+
+const ANNOTATED: &str = "[Event \"Annotated\"]
+[Site \"?\"]
+[Date \"2024.01.02\"]
+[Round \"1\"]
+[White \"alice\"]
+[Black \"bob\"]
+[Result \"1-0\"]
+
+{Opening} 1. e4 e5 2. Nf3 $1 {Attacks the pawn} 2... Nc6 (2... d6 3. d4
+(3. Bc4) 3... exd4) 3. Bb5 a6 1-0
+";
+
+#[test]
+fn read_games() {
+    let text = format!("{}\n[Event \"Second\"]\n\n1. d4 d5 2. c4?! *\n", ANNOTATED);
+    let games = read_pgn(&text).unwrap();
+    assert_eq!(games.len(), 2);
+
+    let game = &games[0];
+    assert_eq!(game.tag("White"), Some("alice"));
+    assert_eq!(game.result, PgnResult::WhiteWins);
+    assert_eq!(game.moves.len(), 6);
+    assert_eq!(game.moves[0].comment_before.as_deref(), Some("Opening"));
+    assert_eq!(game.moves[2].nags, vec![1]);
+    assert_eq!(game.moves[2].comment.as_deref(), Some("Attacks the pawn"));
+    assert_eq!(game.moves[3].variations.len(), 1);
+    assert_eq!(game.moves[3].variations[0][1].variations.len(), 1);
+    assert_eq!(game.end_position().to_fen(), "r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4");
+
+    // writing the game again gives the same text
+    assert_eq!(game.to_pgn(), ANNOTATED);
+
+    assert_eq!(games[1].result, PgnResult::Unfinished);
+    assert_eq!(games[1].moves[2].nags, vec![6]);
+}
+
+#[test]
+fn read_errors() {
+    let error = |text: &str| {
+        let error = read_pgn(text).err().unwrap();
+        (error.line, error.column, error.kind)
+    };
+
+    assert_eq!(error("1. e4 e5\n2. Nf3 Nf6 3. Ke3"), (2, 15, PgnErrorKind::InvalidMove(NotationError::Illegal("Ke3".to_string()))));
+    assert_eq!(error("[Event \"x\"]\n\n1. e4 {open"), (3, 7, PgnErrorKind::UnterminatedComment));
+    assert_eq!(error("[Event x]"), (1, 8, PgnErrorKind::MalformedTag));
+    assert_eq!(error("[FEN \"8/8 w - -\"]"), (1, 6, PgnErrorKind::InvalidFen(FenError::RankCount(2))));
+    assert_eq!(error("1. e4 (1. d4 *"), (1, 14, PgnErrorKind::UnexpectedToken("*".to_string())));
+    assert_eq!(error("1. e4 (1. d4"), (1, 11, PgnErrorKind::UnclosedVariation));
+    assert_eq!(error("1. e4 e5 )"), (1, 10, PgnErrorKind::UnmatchedVariationEnd));
+    assert_eq!(error("$1 1. e4"), (1, 1, PgnErrorKind::MissingMove("$1".to_string())));
+    assert_eq!(error("1. e4 &"), (1, 7, PgnErrorKind::UnexpectedCharacter('&')));
+}
@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use chess_core::{Position, SearchLimits, SearchOptions, Searcher, Style, SKILLS};
+
+fn best_move(fen: &str, depth: u32) -> String {
+    let position = Position::from_fen(fen).unwrap();
+    let result = Searcher::new().search(&position, SearchLimits { depth: Some(depth), ..Default::default() });
+    result.best_move.unwrap().to_string()
+}
+
+#[test]
+fn evaluation_is_symmetric() {
+    assert_eq!(Position::new().evaluate(), 0);
+
+    // the same position with the colors swapped and the board mirrored
+    let white = Position::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+    let black = Position::from_fen("rnbqkb1r/pppp1ppp/5n2/4p3/4P3/2N5/PPPP1PPP/R1BQKBNR b KQkq - 2 3").unwrap();
+    assert_eq!(white.evaluate(), black.evaluate());
+    for style in Style::ALL.iter() {
+        assert_eq!(white.evaluate_style(*style), black.evaluate_style(*style));
+    }
+}
+
+#[test]
+fn finds_mates() {
+    // back rank mate in one
+    assert_eq!(best_move("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3), "a1a8");
+    // mate in two with a queen sacrifice
+    assert_eq!(best_move("r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - 1 1", 4), "d5d8");
+
+    let position = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    let result = Searcher::new().search(&position, SearchLimits { depth: Some(3), ..Default::default() });
+    assert_eq!(result.mate_in(), Some(1));
+}
+
+#[test]
+fn wins_material() {
+    // the queen on d5 hangs
+    assert_eq!(best_move("rnb1kbnr/pppp1ppp/8/3q4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3", 3), "e4d5");
+    // the pawn promotes to a knight with a fork of the king and the queen
+    assert_eq!(best_move("8/2q1P1k1/8/8/8/8/8/4K3 w - - 0 1", 4), "e7e8n");
+}
+
+#[test]
+fn limits() {
+    let position = Position::new();
+
+    let result = Searcher::new().search(&position, SearchLimits { depth: Some(3), ..Default::default() });
+    assert_eq!(result.depth, 3);
+    assert_eq!(result.pv.len(), 3);
+    assert_eq!(result.pv[0], result.best_move.unwrap());
+
+    let result = Searcher::new().search(&position, SearchLimits { nodes: Some(5000), ..Default::default() });
+    assert!(result.nodes < 7000);
+    assert!(result.best_move.is_some());
+
+    let result = Searcher::new().search(&position, SearchLimits { time: Some(Duration::from_millis(200)), ..Default::default() });
+    assert!(result.elapsed < Duration::from_millis(400));
+    assert!(result.best_move.is_some());
+}
+
+#[test]
+fn game_over() {
+    let checkmate = Position::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1").unwrap();
+    let result = Searcher::new().search(&checkmate, SearchLimits { depth: Some(2), ..Default::default() });
+    assert!(result.best_move.is_none());
+}
+
+#[test]
+fn noise() {
+    // a knight for free, even the weakest level sees it most of the time but not always
+    let position = Position::from_fen("rnbqkb1r/pppppppp/8/4n3/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+    let limits = SearchLimits { depth: Some(2), ..Default::default() };
+
+    let result = Searcher::new().search(&position, limits.clone());
+    assert_eq!(result.best_move.unwrap().to_string(), "d4e5");
+
+    let options = SearchOptions { noise: 400, ..Default::default() };
+    for _ in 0..20 {
+        let result = Searcher::with_options(options).search(&position, limits.clone());
+        assert!(position.is_legal(result.best_move.unwrap()));
+        // the reported score leaves out the noise
+        assert!(result.score < 500);
+    }
+}
+
+#[test]
+fn skills() {
+    assert!(SKILLS.windows(2).all(|skills| skills[0].elo < skills[1].elo && skills[0].noise > skills[1].noise));
+
+    let position = Position::new();
+    let result = Searcher::with_options(SearchOptions { noise: SKILLS[0].noise, style: Style::Aggressive, ..Default::default() }).search(&position, SKILLS[0].limits());
+    assert_eq!(result.depth, 1);
+}
+
+#[test]
+fn multi_pv() {
+    let position = Position::new();
+    let options = SearchOptions { multi_pv: 3, ..Default::default() };
+    let result = Searcher::with_options(options).search(&position, SearchLimits { depth: Some(3), ..Default::default() });
+    assert_eq!(result.lines.len(), 3);
+    assert_eq!(result.lines[0].pv, result.pv);
+    assert!(result.lines.windows(2).all(|lines| lines[0].score >= lines[1].score && lines[0].pv[0] != lines[1].pv[0]));
+    assert_ne!(result.lines[0].pv[0], result.lines[2].pv[0]);
+
+    // only the given moves are searched, there are fewer of them than variations asked for
+    let moves = vec![position.parse_uci("a2a3").unwrap(), position.parse_uci("h2h3").unwrap()];
+    let options = SearchOptions { multi_pv: 5, ..Default::default() };
+    let result = Searcher::with_options(options).search(&position, SearchLimits { depth: Some(3), moves: moves.clone(), ..Default::default() });
+    assert_eq!(result.lines.len(), 2);
+    assert!(moves.contains(&result.best_move.unwrap()));
+}
@@ -0,0 +1,259 @@
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{atomic::Ordering, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chess_core::{EngineInfo, GoParams, Position, Score, UciCommand, UciCommandError, UciEngine, UciError};
+
+// the scripted engine from examples/mock_engine.rs, cargo test builds the examples next to the test binaries
+fn mock_engine() -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    if path.ends_with("deps") {
+        path.pop();
+    }
+    path.push("examples");
+    path.push(format!("mock_engine{}", std::env::consts::EXE_SUFFIX));
+    assert!(path.exists(), "{} is missing, run the tests with cargo test to build the examples", path.display());
+    path
+}
+
+fn start(mode: &str) -> Result<UciEngine, UciError> {
+    UciEngine::start(mock_engine(), &[mode.to_string()])
+}
+
+fn movetime(millis: u64) -> GoParams {
+    GoParams {
+        movetime: Some(Duration::from_millis(millis)),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn handshake() {
+    let mut engine = start("normal").unwrap();
+    assert_eq!(engine.name(), "Mock Engine");
+    assert_eq!(engine.author(), Some("chess-core tests"));
+    assert!(engine.has_option("hash"));
+    assert_eq!(engine.options()[0].default.as_deref(), Some("16"));
+    assert_eq!(engine.options()[1].name, "Clear Hash");
+    engine.set_option("Hash", "32").unwrap();
+    engine.new_game().unwrap();
+
+    assert_eq!(start("mute").err(), Some(UciError::Timeout("uciok")));
+    assert!(matches!(UciEngine::start("/nonexistent/engine", &[]), Err(UciError::Spawn(_))));
+}
+
+#[test]
+fn best_move() {
+    let mut engine = start("normal").unwrap();
+    let start_position = Position::new();
+    let e4 = start_position.parse_uci("e2e4").unwrap();
+
+    let mut infos = Vec::new();
+    let mv = engine.go(&start_position, &[e4], &movetime(100), |info| infos.push(info.clone())).unwrap();
+    let mut position = start_position.clone();
+    position.make_move(e4);
+    assert!(position.is_legal(mv));
+    assert_eq!(infos.len(), 1);
+    assert_eq!(infos[0].score, Some(Score::Centipawns(13)));
+    assert_eq!(infos[0].pv_moves(&position), vec![mv]);
+
+    // from a fen, where the only move is to take the checking queen
+    let forced = Position::from_fen("k7/1Q6/8/8/8/8/8/7K b - - 0 1").unwrap();
+    let mv = engine.go(&forced, &[], &movetime(100), |_| {}).unwrap();
+    assert_eq!(mv.to_string(), "a8b7");
+
+    let stalemate = Position::from_fen("k7/2Q5/8/8/8/8/8/7K b - - 0 1").unwrap();
+    assert_eq!(engine.go(&stalemate, &[], &movetime(100), |_| {}), Err(UciError::NoMove));
+}
+
+#[test]
+fn misbehaving_engines() {
+    let mut engine = start("illegal").unwrap();
+    assert_eq!(engine.go(&Position::new(), &[], &movetime(100), |_| {}), Err(UciError::IllegalMove("a1a8".to_string())));
+
+    // a crashed engine can be started again
+    let mut engine = start("crash").unwrap();
+    assert_eq!(engine.go(&Position::new(), &[], &movetime(100), |_| {}), Err(UciError::Crashed));
+    engine.restart().unwrap();
+    assert!(engine.is_running());
+    engine.is_ready().unwrap();
+
+    // a hanging engine is told to stop and given a little more time
+    let mut engine = start("hang").unwrap();
+    let start_time = Instant::now();
+    assert_eq!(engine.go(&Position::new(), &[], &movetime(100), |_| {}), Err(UciError::Timeout("bestmove")));
+    assert!(start_time.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn info_lines() {
+    let info = EngineInfo::parse("depth 12 seldepth 18 multipv 2 score mate -3 upperbound nodes 123456 nps 987654 hashfull 250 time 125 pv e2e4 e7e5 g1f3");
+    assert_eq!(info.depth, Some(12));
+    assert_eq!(info.seldepth, Some(18));
+    assert_eq!(info.multipv, Some(2));
+    assert_eq!(info.score, Some(Score::Mate(-3)));
+    assert!(info.upperbound && !info.lowerbound);
+    assert_eq!(info.nodes, Some(123456));
+    assert_eq!(info.time, Some(Duration::from_millis(125)));
+    assert_eq!(info.pv, vec!["e2e4", "e7e5", "g1f3"]);
+    assert_eq!(info.to_string(), "info depth 12 seldepth 18 multipv 2 score mate -3 upperbound nodes 123456 nps 987654 hashfull 250 time 125 pv e2e4 e7e5 g1f3");
+
+    // the variation stops at the first illegal move
+    let info = EngineInfo::parse("score cp -40 pv e2e4 e2e4");
+    assert_eq!(info.pv_moves(&Position::new()).len(), 1);
+
+    let info = EngineInfo::parse("string hello from the engine");
+    assert_eq!(info.string.as_deref(), Some("hello from the engine"));
+    assert_eq!(info.score, None);
+}
+
+#[test]
+fn go_commands() {
+    let params = GoParams::parse("wtime 60000 btime 55000 winc 1000 binc 1000 movestogo 20");
+    assert_eq!(params.wtime, Some(Duration::from_secs(60)));
+    assert_eq!(params.binc, Some(Duration::from_secs(1)));
+    assert_eq!(params.movestogo, Some(20));
+    assert_eq!(params.to_command(), "go wtime 60000 btime 55000 winc 1000 binc 1000 movestogo 20");
+
+    let params = GoParams::parse("searchmoves e2e4 d2d4 depth 5 infinite");
+    assert_eq!(params.searchmoves, vec!["e2e4", "d2d4"]);
+    assert_eq!(params.depth, Some(5));
+    assert!(params.infinite);
+    assert_eq!(GoParams::parse(&params.to_command()["go".len()..]), params);
+}
+
+#[test]
+fn commands() {
+    let position = match UciCommand::parse("position startpos moves e2e4 e7e5") {
+        Ok(UciCommand::Position(position)) => position,
+        _ => panic!("expected a position")
+    };
+    assert_eq!(position.to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2");
+
+    let fen = "8/8/8/4k3/8/8/4P3/4K3 w - - 0 1";
+    match UciCommand::parse(&format!("position fen {} moves e2e4", fen)) {
+        Ok(UciCommand::Position(position)) => assert_eq!(position.to_fen(), "8/8/8/4k3/4P3/8/8/4K3 b - e3 0 1"),
+        _ => panic!("expected a position")
+    }
+
+    match UciCommand::parse("setoption name Clear Hash") {
+        Ok(UciCommand::SetOption { name, value }) => assert_eq!((name.as_str(), value), ("Clear Hash", None)),
+        _ => panic!("expected an option")
+    }
+    match UciCommand::parse("setoption name Hash value 64") {
+        Ok(UciCommand::SetOption { name, value }) => assert_eq!((name.as_str(), value.as_deref()), ("Hash", Some("64"))),
+        _ => panic!("expected an option")
+    }
+    match UciCommand::parse("go movetime 500") {
+        Ok(UciCommand::Go(params)) => assert_eq!(params.movetime, Some(Duration::from_millis(500))),
+        _ => panic!("expected go")
+    }
+
+    assert!(matches!(UciCommand::parse("position startpos moves e2e5"), Err(UciCommandError::Move(_))));
+    assert!(matches!(UciCommand::parse("position fen 8/8/8 w - - 0 1"), Err(UciCommandError::Fen(_))));
+    assert_eq!(UciCommand::parse("position").err(), Some(UciCommandError::Incomplete("position".to_string())));
+    assert_eq!(UciCommand::parse("fly").err(), Some(UciCommandError::Unknown("fly".to_string())));
+}
+
+// our own engine, driven through the client
+fn chess_uci() -> UciEngine {
+    UciEngine::start(env!("CARGO_BIN_EXE_chess-uci"), &[]).unwrap()
+}
+
+#[test]
+fn engine_mode() {
+    let mut engine = chess_uci();
+    assert!(engine.name().starts_with("chess-core"));
+    for option in ["Hash", "Threads", "MultiPV", "Ponder", "Clear Hash"].iter() {
+        assert!(engine.has_option(option), "{}", option);
+    }
+    engine.set_option("Hash", "8").unwrap();
+    engine.set_option("Threads", "1").unwrap();
+    engine.new_game().unwrap();
+
+    let start = Position::new();
+    let mut infos = Vec::new();
+    let params = GoParams { depth: Some(3), ..Default::default() };
+    let mv = engine.go(&start, &[], &params, |info| infos.push(info.clone())).unwrap();
+    assert!(start.is_legal(mv));
+    let last = infos.last().unwrap();
+    assert_eq!(last.depth, Some(3));
+    assert!(last.nodes.is_some() && last.time.is_some() && last.score.is_some());
+    assert_eq!(last.pv_moves(&start)[0], mv);
+
+    // three variations at every depth
+    engine.set_option("MultiPV", "3").unwrap();
+    let mut infos = Vec::new();
+    engine.go(&start, &[], &params, |info| infos.push(info.clone())).unwrap();
+    let deepest: Vec<&EngineInfo> = infos.iter().filter(|info| info.depth == Some(3)).collect();
+    assert_eq!(deepest.iter().map(|info| info.multipv.unwrap()).collect::<Vec<_>>(), vec![1, 2, 3]);
+    engine.set_option("MultiPV", "1").unwrap();
+
+    // the clock, the movetime and the node count all end the search
+    let e4 = start.parse_uci("e2e4").unwrap();
+    let clock = GoParams { wtime: Some(Duration::from_millis(3000)), btime: Some(Duration::from_millis(3000)), ..Default::default() };
+    let begin = Instant::now();
+    engine.go(&start, &[e4], &clock, |_| {}).unwrap();
+    assert!(begin.elapsed() < Duration::from_millis(1000));
+    let begin = Instant::now();
+    engine.go(&start, &[e4], &movetime(200), |_| {}).unwrap();
+    assert!(begin.elapsed() < Duration::from_millis(600));
+    let nodes = GoParams { nodes: Some(2000), ..Default::default() };
+    engine.go(&start, &[e4], &nodes, |info| assert!(info.nodes.unwrap() < 4000)).unwrap();
+
+    // an infinite search goes on until it is stopped
+    let stop = engine.stop_handle();
+    let stopper = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(300));
+        stop.store(true, Ordering::Relaxed);
+    });
+    let begin = Instant::now();
+    let infinite = GoParams { infinite: true, ..Default::default() };
+    assert!(start.is_legal(engine.go(&start, &[], &infinite, |_| {}).unwrap()));
+    assert!(begin.elapsed() >= Duration::from_millis(300));
+    stopper.join().unwrap();
+
+    let checkmate = Position::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1").unwrap();
+    assert_eq!(engine.go(&checkmate, &[], &params, |_| {}), Err(UciError::NoMove));
+}
+
+// ponderhit isn't part of the client, so the pipes are driven by hand
+#[test]
+fn engine_mode_ponder() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chess-uci")).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            sender.send(line.unwrap()).unwrap();
+        }
+    });
+    let best_move = |timeout: Duration| loop {
+        match lines.recv_timeout(timeout) {
+            Ok(line) if line.starts_with("bestmove") => return Some(line),
+            Ok(_) => {}
+            Err(_) => return None
+        }
+    };
+
+    writeln!(stdin, "position startpos moves e2e4 e7e5").unwrap();
+    writeln!(stdin, "go ponder wtime 3000 btime 3000").unwrap();
+    // no best move while pondering, however long it takes
+    assert_eq!(best_move(Duration::from_millis(500)), None);
+    writeln!(stdin, "ponderhit").unwrap();
+    let line = best_move(Duration::from_secs(2)).unwrap();
+    assert!(line.starts_with("bestmove "));
+
+    // a ponder miss is stopped and searched again
+    writeln!(stdin, "go ponder wtime 3000 btime 3000").unwrap();
+    writeln!(stdin, "stop").unwrap();
+    assert!(best_move(Duration::from_secs(2)).is_some());
+
+    writeln!(stdin, "quit").unwrap();
+    assert!(child.wait().unwrap().success());
+}
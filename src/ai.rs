@@ -0,0 +1,481 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use futures_lite::future;
+
+use chess_core::{Clock, EngineInfo, GoParams, Move, OpeningBook, Position, Score, SearchLimits, SearchOptions, Searcher, Skill, Style, UciEngine, UciError, SKILLS};
+
+use crate::{board::*, clock::GameClock, pieces::PieceColor, replay::Replay};
+
+// who moves the pieces of a color
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Player {
+    Human,
+    Computer,
+    // an external uci engine, index into Engines
+    Engine(usize),
+}
+
+// who plays a color and, for the computer, how strong and in which style
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PlayerConfig {
+    pub player: Player,
+    // index into SKILLS
+    pub skill: usize,
+    pub style: Style,
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        PlayerConfig {
+            player: Player::Human,
+            skill: 3,
+            style: Style::Balanced,
+        }
+    }
+}
+
+impl PlayerConfig {
+    pub fn skill(&self) -> &'static Skill {
+        &SKILLS[self.skill.min(SKILLS.len() - 1)]
+    }
+
+    pub fn search_options(&self) -> SearchOptions {
+        SearchOptions { style: self.style, noise: self.skill().noise, ..Default::default() }
+    }
+}
+
+// the players of both colors, people play each other unless a color is given to the computer
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Players {
+    pub white: PlayerConfig,
+    pub black: PlayerConfig,
+}
+
+impl Players {
+    pub fn get(&self, color: PieceColor) -> &PlayerConfig {
+        match color {
+            PieceColor::White => &self.white,
+            PieceColor::Black => &self.black
+        }
+    }
+
+    pub fn get_mut(&mut self, color: PieceColor) -> &mut PlayerConfig {
+        match color {
+            PieceColor::White => &mut self.white,
+            PieceColor::Black => &mut self.black
+        }
+    }
+
+    pub fn is_computer(&self, color: PieceColor) -> bool {
+        self.get(color).player != Player::Human
+    }
+
+    pub fn has_computer(&self) -> bool {
+        self.is_computer(PieceColor::White) || self.is_computer(PieceColor::Black)
+    }
+
+    // the color the buttons resign or offer a draw for: the person's against the computer, the player's to move between
+    // two people and None when the computer plays both colors
+    pub fn person(&self, side_to_move: PieceColor) -> Option<PieceColor> {
+        match (self.is_computer(PieceColor::White), self.is_computer(PieceColor::Black)) {
+            (false, true) => Some(PieceColor::White),
+            (true, false) => Some(PieceColor::Black),
+            (false, false) => Some(side_to_move),
+            (true, true) => None
+        }
+    }
+}
+
+// the computer takes a draw when it stands worse than this many centipawns
+const DRAW_ACCEPT_MARGIN: i32 = 100;
+
+// the computer of the color accepts a draw offer if the evaluation says it is worse
+pub fn accepts_draw(position: &Position, color: PieceColor) -> bool {
+    let score = if position.side_to_move() == color { position.evaluate() } else { -position.evaluate() };
+    score < -DRAW_ACCEPT_MARGIN
+}
+
+// names of uci engines looked for in the directories of PATH
+const KNOWN_ENGINES: [&str; 12] = ["stockfish", "lc0", "komodo", "dragon", "berserk", "ethereal", "rubichess", "koivisto", "igel", "weiss", "fruit", "crafty"];
+
+// a uci engine program on this machine
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EngineProgram {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+// the engines that can play or analyze, the ones given on the command line come first
+#[derive(Clone, Default)]
+pub struct Engines(pub Vec<EngineProgram>);
+
+impl Engines {
+    // the given programs and every known engine found in PATH
+    pub fn find(paths: Vec<PathBuf>) -> Engines {
+        let mut programs: Vec<EngineProgram> = paths.into_iter().map(|path| EngineProgram {
+            name: path.file_stem().map_or_else(|| path.display().to_string(), |stem| stem.to_string_lossy().into_owned()),
+            path,
+        }).collect();
+
+        let directories = std::env::var_os("PATH").map_or_else(Vec::new, |path| std::env::split_paths(&path).collect());
+        for name in KNOWN_ENGINES.iter() {
+            let found = directories.iter().map(|directory| directory.join(format!("{}{}", name, std::env::consts::EXE_SUFFIX))).find(|path| path.is_file());
+            if let Some(path) = found {
+                if !programs.iter().any(|program| program.path == path) {
+                    programs.push(EngineProgram { name: name.to_string(), path });
+                }
+            }
+        }
+        Engines(programs)
+    }
+
+    pub fn name(&self, index: usize) -> &str {
+        self.0.get(index).map_or("engine", |program| &program.name)
+    }
+}
+
+// what plays the moves of a computer color, kept between the moves
+#[allow(clippy::large_enum_variant)]
+enum Thinker {
+    BuiltIn(Searcher),
+    // the engine with the index of the skill it was set to
+    Engine(UciEngine, usize),
+}
+
+// the thinker comes back with its move, for the color that was to move
+type ThinkerTask = Task<(PieceColor, Option<Thinker>, Option<Move>)>;
+
+// search of the computer running on a background thread
+#[derive(Default)]
+struct ComputerSearch {
+    task: Option<ThinkerTask>,
+    // one thinker per color, a searcher keeps its transposition table and an engine keeps running, None while it searches
+    thinkers: [Option<Thinker>; 2],
+    // hash of the position the search started from, a result for another position is thrown away
+    hash: u64,
+    stop: Arc<AtomicBool>,
+}
+
+// the built-in opening book, the computer plays from it as long as the game follows a known line
+struct Book(OpeningBook);
+
+impl Default for Book {
+    fn default() -> Self {
+        Book(OpeningBook::builtin())
+    }
+}
+
+// the latest line of the shown position from the engine analyzing it
+#[derive(Default)]
+pub struct Analysis {
+    // index into Engines, None while no engine analyzes
+    pub engine: Option<usize>,
+    pub text: String,
+    shared: Option<Arc<Mutex<AnalysisShared>>>,
+}
+
+// talks between the ui and the thread running the engine
+struct AnalysisShared {
+    position: Position,
+    // goes up whenever the position changes, an info of an older position is not shown
+    generation: u64,
+    info: Option<(u64, EngineInfo)>,
+    // stops the search of the engine when the position changes
+    stop: Option<Arc<AtomicBool>>,
+    error: Option<String>,
+    quit: bool,
+}
+
+#[derive(Default)]
+pub struct AiPlugin {
+    pub players: Players,
+    pub engines: Engines,
+    // the engine that analyzes from the start
+    pub analysis: Option<usize>,
+}
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_resource(self.players).add_resource(self.engines.clone()).add_resource(Analysis { engine: self.analysis, ..Default::default() })
+        .init_resource::<ComputerSearch>().init_resource::<Book>()
+        .add_system(start_search.system()).add_system(finish_search.system())
+        .add_system(analysis_keys.system()).add_system(run_analysis.system());
+    }
+}
+
+// plays a book move or starts a search whenever the computer is to move
+#[allow(clippy::too_many_arguments)]
+fn start_search(mut position: ResMut<Position>, mut move_list: ResMut<MoveList>, start_position: Res<StartPosition>, players: Res<Players>, engines: Res<Engines>, book: Res<Book>, game_clock: Res<GameClock>, game_result: Res<GameResult>, replay: Res<Replay>, promotion: Res<Promotion>, takeback: Res<Takeback>, pool: Res<AsyncComputeTaskPool>, mut search: ResMut<ComputerSearch>) {
+    if search.task.is_some() || !players.is_computer(position.side_to_move()) {
+        return;
+    }
+    if game_result.0.is_some() || replay.is_active() || promotion.pending_move.is_some() || takeback.requested_by.is_some() {
+        return;
+    }
+
+    let color = position.side_to_move();
+    let config = *players.get(color);
+
+    // engines play their own openings
+    let random = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+    if let Some(mv) = book.0.pick(&position, random).filter(|_| config.player == Player::Computer) {
+        play_move(&mut position, &mut move_list, mv);
+        return;
+    }
+
+    let thinker = search.thinkers[color as usize].take();
+    let search_position = position.clone();
+    let clock = game_clock.clock.as_ref().map(Clock::go_params);
+    search.hash = position.hash();
+
+    let program = match config.player {
+        Player::Engine(index) => engines.0.get(index).cloned(),
+        _ => None
+    };
+    if let Some(program) = program {
+        // a running engine is kept unless the program or the skill changed
+        let engine = match thinker {
+            Some(Thinker::Engine(engine, skill)) if engine.path() == program.path && skill == config.skill => Some(engine),
+            _ => None
+        };
+        search.stop = engine.as_ref().map_or_else(Default::default, |engine| engine.stop_handle());
+        let start = start_position.0.clone();
+        let moves: Vec<Move> = move_list.moves.iter().map(|recorded| recorded.mv).collect();
+        search.task = Some(pool.spawn(async move {
+            let (engine, mv) = engine_move(&program, engine, config, &start, &moves, &search_position, clock);
+            (color, engine.map(|engine| Thinker::Engine(engine, config.skill)), mv)
+        }));
+    } else {
+        let mut searcher = match thinker {
+            Some(Thinker::BuiltIn(searcher)) => searcher,
+            _ => Searcher::default()
+        };
+        searcher.set_options(config.search_options());
+        search.stop = searcher.stop_handle();
+        let limits = search_limits(&config, clock.as_ref(), color);
+        search.task = Some(pool.spawn(async move {
+            let result = searcher.search(&search_position, limits);
+            (color, Some(Thinker::BuiltIn(searcher)), result.best_move)
+        }));
+    }
+}
+
+// the limits of the skill, with a clock the computer thinks no longer than its share of the time left
+fn search_limits(config: &PlayerConfig, clock: Option<&GoParams>, color: PieceColor) -> SearchLimits {
+    let mut limits = config.skill().limits();
+    if let Some(time) = clock.and_then(|params| params.time_for_move(color)) {
+        limits.time = limits.time.map(|limit| limit.min(time));
+    }
+    limits
+}
+
+// starts the engine and lets it play at the strength of the skill if it can
+fn start_engine(program: &EngineProgram, skill: &Skill) -> Result<UciEngine, UciError> {
+    let mut engine = UciEngine::start(&program.path, &[])?;
+    if engine.has_option("UCI_LimitStrength") && engine.has_option("UCI_Elo") {
+        engine.set_option("UCI_LimitStrength", "true")?;
+        engine.set_option("UCI_Elo", &skill.elo.to_string())?;
+    }
+    engine.new_game()?;
+    Ok(engine)
+}
+
+// the move of the engine, which is started if it isn't running and restarted once if it crashes or hangs,
+// the built-in search plays instead if the engine keeps failing, with a clock the engine gets the times of both players
+fn engine_move(program: &EngineProgram, engine: Option<UciEngine>, config: PlayerConfig, start: &Position, moves: &[Move], position: &Position, clock: Option<GoParams>) -> (Option<UciEngine>, Option<Move>) {
+    let fallback = || Searcher::with_options(config.search_options()).search(position, search_limits(&config, clock.as_ref(), position.side_to_move())).best_move;
+    let mut engine = match engine.map_or_else(|| start_engine(program, config.skill()), Ok) {
+        Ok(engine) => engine,
+        Err(error) => {
+            warn!("couldn't start {}: {}", program.name, error);
+            return (None, fallback());
+        }
+    };
+
+    let params = clock.clone().unwrap_or(GoParams { movetime: Some(config.skill().time), ..Default::default() });
+    let mut result = engine.go(start, moves, &params, |_| {});
+    if let Err(error @ UciError::Crashed) | Err(error @ UciError::Timeout(_)) = &result {
+        warn!("{}: {}, starting it again", engine.name(), error);
+        result = engine.restart().and_then(|_| engine.go(start, moves, &params, |_| {}));
+    }
+    match result {
+        Ok(mv) => (Some(engine), Some(mv)),
+        Err(error) => {
+            warn!("{}: {}, the built-in search plays this move", engine.name(), error);
+            (None, fallback())
+        }
+    }
+}
+
+// plays the move of the finished search the same way a player's move is played
+fn finish_search(mut search: ResMut<ComputerSearch>, mut position: ResMut<Position>, mut move_list: ResMut<MoveList>, game_result: Res<GameResult>, replay: Res<Replay>) {
+    if search.task.is_none() {
+        return;
+    }
+
+    // the game went on without the computer, after an undo or a new game, the search is stopped but the thinker is kept
+    let abandoned = game_result.0.is_some() || replay.is_active() || position.hash() != search.hash;
+    if abandoned {
+        search.stop.store(true, Ordering::Relaxed);
+    }
+
+    let finished = search.task.as_mut().and_then(|task| future::block_on(future::poll_once(task)));
+    if let Some((color, thinker, mv)) = finished {
+        search.task = None;
+        search.thinkers[color as usize] = thinker;
+        if abandoned {
+            // a stop that came after the search returned mustn't stop the next one right away
+            search.stop.store(false, Ordering::Relaxed);
+            return;
+        }
+        if let Some(mv) = mv.filter(|mv| position.is_legal(*mv)) {
+            play_move(&mut position, &mut move_list, mv);
+        }
+    }
+}
+
+// ctrl+a starts and stops the analysis with the first engine
+fn analysis_keys(keyboard_input: Res<Input<KeyCode>>, engines: Res<Engines>, mut analysis: ResMut<Analysis>) {
+    let ctrl = keyboard_input.pressed(KeyCode::LControl) || keyboard_input.pressed(KeyCode::RControl);
+    if !ctrl || !keyboard_input.just_pressed(KeyCode::A) {
+        return;
+    }
+    if analysis.engine.is_some() {
+        analysis.engine = None;
+    } else if engines.0.is_empty() {
+        warn!("no uci engine to analyze with, give one with --engine <path>");
+    } else {
+        analysis.engine = Some(0);
+    }
+}
+
+// keeps the analyzing engine on the shown position and the text on its latest line
+fn run_analysis(mut analysis: ResMut<Analysis>, engines: Res<Engines>, displayed_position: Res<DisplayedPosition>) {
+    let program = analysis.engine.and_then(|index| engines.0.get(index).cloned());
+    let program = match program {
+        Some(program) => program,
+        None => {
+            if let Some(shared) = analysis.shared.take() {
+                let mut shared = shared.lock().unwrap();
+                shared.quit = true;
+                if let Some(stop) = &shared.stop {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            }
+            if !analysis.text.is_empty() {
+                analysis.text.clear();
+            }
+            return;
+        }
+    };
+
+    let shared = analysis.shared.get_or_insert_with(|| {
+        let shared = Arc::new(Mutex::new(AnalysisShared {
+            position: displayed_position.0.clone(),
+            generation: 0,
+            info: None,
+            stop: None,
+            error: None,
+            quit: false,
+        }));
+        let thread_shared = shared.clone();
+        thread::spawn(move || analyze(program, thread_shared));
+        shared
+    }).clone();
+
+    let text = {
+        let mut shared = shared.lock().unwrap();
+        if shared.position.hash() != displayed_position.0.hash() {
+            shared.position = displayed_position.0.clone();
+            shared.generation += 1;
+            if let Some(stop) = &shared.stop {
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+        match (&shared.error, &shared.info) {
+            (Some(error), _) => format!("{}: {}", engines.name(analysis.engine.unwrap_or(0)), error),
+            (None, Some((generation, info))) if *generation == shared.generation => analysis_text(engines.name(analysis.engine.unwrap_or(0)), &shared.position, info),
+            _ => format!("{}: thinking", engines.name(analysis.engine.unwrap_or(0)))
+        }
+    };
+    // only set when it changed, so the ui is only told about new lines
+    if analysis.text != text {
+        analysis.text = text;
+    }
+}
+
+// "stockfish depth 18 +0.35 e4 e5 Nf3", the score is from the view of white
+fn analysis_text(name: &str, position: &Position, info: &EngineInfo) -> String {
+    let sign = if position.side_to_move() == PieceColor::White { 1 } else { -1 };
+    let score = match info.score {
+        Some(Score::Centipawns(centipawns)) => format!("{:+.2}", (sign * centipawns) as f32 / 100.),
+        Some(Score::Mate(moves)) => format!("#{}", sign * moves),
+        None => "?".to_string()
+    };
+
+    let mut line = position.clone();
+    let mut sans = Vec::new();
+    for mv in info.pv_moves(position).into_iter().take(8) {
+        sans.push(line.san(mv));
+        line.make_move(mv);
+    }
+    format!("{} depth {} {} {}", name, info.depth.unwrap_or(0), score, sans.join(" "))
+}
+
+// runs the engine on its own thread until the analysis ends, the search starts again whenever the position changes
+fn analyze(program: EngineProgram, shared: Arc<Mutex<AnalysisShared>>) {
+    let mut engine = match UciEngine::start(&program.path, &[]) {
+        Ok(engine) => engine,
+        Err(error) => {
+            shared.lock().unwrap().error = Some(error.to_string());
+            return;
+        }
+    };
+    shared.lock().unwrap().stop = Some(engine.stop_handle());
+
+    let params = GoParams { infinite: true, ..Default::default() };
+    let mut analyzed = None;
+    loop {
+        let (position, generation) = {
+            let shared = shared.lock().unwrap();
+            if shared.quit {
+                return;
+            }
+            // a stop for an earlier position that came while no search ran mustn't stop the search of this one
+            if analyzed != Some(shared.generation) {
+                if let Some(stop) = &shared.stop {
+                    stop.store(false, Ordering::Relaxed);
+                }
+            }
+            (shared.position.clone(), shared.generation)
+        };
+        // a finished game or a search that ended by itself waits for the next position
+        if analyzed == Some(generation) || position.game_end().is_some() {
+            thread::sleep(Duration::from_millis(50));
+            continue;
+        }
+        analyzed = Some(generation);
+
+        let result = engine.go(&position, &[], &params, |info| {
+            if !info.pv.is_empty() && info.multipv.unwrap_or(1) == 1 {
+                shared.lock().unwrap().info = Some((generation, info.clone()));
+            }
+        });
+        if let Err(UciError::Crashed) | Err(UciError::Timeout(_)) = result {
+            warn!("{} stopped analyzing, starting it again", engine.name());
+            let restarted = engine.restart();
+            let mut shared = shared.lock().unwrap();
+            match restarted {
+                Ok(()) => shared.stop = Some(engine.stop_handle()),
+                Err(error) => {
+                    shared.error = Some(error.to_string());
+                    return;
+                }
+            }
+        }
+    }
+}
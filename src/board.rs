@@ -1,8 +1,12 @@
+use std::time::{Duration, SystemTime};
+
 use bevy::prelude::*;
-use bevy::app::AppExit;
+use bevy::render::{mesh::Indices, pipeline::PrimitiveTopology};
 use bevy_mod_picking::*;
 
-use crate::pieces::*;
+use chess_core::{GameEnd, Move, Position};
+
+use crate::{ai::{accepts_draw, Players}, pieces::*, replay::Replay};
 
 pub struct Square {
     pub x: u8,
@@ -11,10 +15,121 @@ pub struct Square {
 
 impl Square {
     fn is_white(&self) -> bool {
-        (self.x + self.y + 1) % 2 == 0
+        (self.x + self.y) % 2 == 1
+    }
+}
+
+// colors of the board, the squares are tinted for the selection, the last move and a king in check
+pub struct BoardTheme {
+    pub light_square: Color,
+    pub dark_square: Color,
+    // square under the cursor
+    pub hovered: Color,
+    pub selected: Color,
+    // squares the last move came from and went to
+    pub last_move: Color,
+    // square of the king in check
+    pub check: Color,
+    // dots and rings on the squares the selected piece can move to
+    pub move_marker: Color,
+}
+
+impl Default for BoardTheme {
+    fn default() -> Self {
+        BoardTheme {
+            light_square: Color::rgb(1., 0.9, 0.9),
+            dark_square: Color::rgb(0., 0.1, 0.1),
+            hovered: Color::rgb(0.8, 0.3, 0.3),
+            selected: Color::rgb(0.9, 0.1, 0.1),
+            last_move: Color::rgb(0.8, 0.7, 0.3),
+            check: Color::rgb(1., 0.4, 0.),
+            move_marker: Color::rgb(0.2, 0.6, 0.3),
+        }
+    }
+}
+
+// component for the dots and rings that show where the selected piece can move
+struct MoveMarker;
+
+// a dot for a move to an empty square and a ring for a capture, the ring goes around the piece
+struct MarkerMeshes {
+    dot: Handle<Mesh>,
+    ring: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+impl FromResources for MarkerMeshes {
+    fn from_resources(resources: &Resources) -> Self {
+        let mut meshes = resources.get_mut::<Assets<Mesh>>().unwrap();
+        let mut materials = resources.get_mut::<Assets<StandardMaterial>>().unwrap();
+        let theme = resources.get::<BoardTheme>().unwrap();
+        MarkerMeshes {
+            dot: meshes.add(ring_mesh(0., 0.15)),
+            ring: meshes.add(ring_mesh(0.4, 0.48)),
+            material: materials.add(theme.move_marker.into()),
+        }
+    }
+}
+
+// a flat ring lying on the board around its center, a disc with an inner radius of 0
+fn ring_mesh(inner_radius: f32, outer_radius: f32) -> Mesh {
+    const SEGMENTS: u32 = 32;
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+
+    for i in 0..SEGMENTS {
+        let (sin, cos) = (i as f32 / SEGMENTS as f32 * std::f32::consts::TAU).sin_cos();
+        for radius in [inner_radius, outer_radius].iter() {
+            positions.push([cos * radius, 0., sin * radius]);
+            normals.push([0., 1., 0.]);
+            uvs.push([0.5 + cos * radius / outer_radius / 2., 0.5 + sin * radius / outer_radius / 2.]);
+        }
+        // two triangles to the next segment, facing up
+        let next = (i + 1) % SEGMENTS;
+        indices.extend_from_slice(&[i * 2, next * 2 + 1, i * 2 + 1, i * 2, next * 2, next * 2 + 1]);
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh
+}
+
+// how the player moves the pieces with the mouse
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum MoveInput {
+    // click the piece and then the square it moves to
+    ClickClick,
+    // drag the piece to its square and drop it there
+    DragAndDrop,
+    // either of them
+    #[default]
+    Both,
+}
+
+impl MoveInput {
+    fn click(self) -> bool {
+        self != MoveInput::DragAndDrop
+    }
+
+    fn drag(self) -> bool {
+        self != MoveInput::ClickClick
     }
 }
 
+// the piece that follows the cursor while the mouse button is held, move_pieces leaves it alone
+#[derive(Default)]
+pub struct DraggedPiece {
+    pub entity: Option<Entity>,
+}
+
+// a dragged piece is lifted this high above the board
+const DRAG_HEIGHT: f32 = 0.3;
+
 #[derive(Default)]
 struct SelectedSquare {
     entity: Option<Entity>,
@@ -25,6 +140,7 @@ struct SelectedPiece {
     entity: Option<Entity>,
 }
 
+// color of the player to move, mirrors the side to move of the position
 pub struct PlayerTurn(pub PieceColor);
 
 impl Default for PlayerTurn {
@@ -33,38 +149,150 @@ impl Default for PlayerTurn {
     }
 }
 
-pub struct BoardPlugin;
+// result of the game, None while it is still running
+#[derive(Default)]
+pub struct GameResult(pub Option<GameEnd>);
+
+// sent by the ui when the player to move claims a draw
+pub struct ClaimDraw;
+
+// sent by the ui to reset the board for a new game
+pub struct NewGame;
+
+// pawn move to the last row that waits for the player to choose the new piece
+#[derive(Default)]
+pub struct Promotion {
+    pub pending_move: Option<Move>,
+}
+
+// sent by the ui with the piece type the player chose for the promotion
+pub struct PromotionChoice(pub PieceType);
+
+// a move of the game, kept for the move history and the pgn export
+pub struct RecordedMove {
+    pub mv: Move,
+    pub san: String,
+    // time left on the clock of the player who moved, if the game is played with a clock
+    pub clock: Option<Duration>,
+}
+
+// every move played since the start position
+pub struct MoveList {
+    pub moves: Vec<RecordedMove>,
+    // moves that were taken back, redo plays the last one again
+    pub undone: Vec<RecordedMove>,
+    pub started: SystemTime,
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        MoveList {
+            moves: Vec::new(),
+            undone: Vec::new(),
+            started: SystemTime::now(),
+        }
+    }
+}
+
+// sent by the ui and the keyboard to take back the last move
+pub struct UndoMove;
+
+// sent by the ui and the keyboard to play the last move that was taken back again
+pub struct RedoMove;
+
+// in a game between two people the opponent has to agree before a move is taken back
+#[derive(Default)]
+pub struct Takeback {
+    // color of the player who wants to take back their last move
+    pub requested_by: Option<PieceColor>,
+}
+
+// sent by the ui when the opponent accepts or declines the takeback
+pub struct TakebackAnswer(pub bool);
+
+// sent by the ui and the keyboard when a player gives up the game
+pub struct Resign;
+
+// sent by the ui and the keyboard when a player offers a draw
+pub struct OfferDraw;
+
+// sent by the ui and the keyboard when the opponent accepts or declines the draw offer
+pub struct DrawAnswer(pub bool);
+
+// sent by the ui and the keyboard to call the game off
+pub struct Abort;
+
+// a draw offer waits for the opponent, who accepts it or declines it by making their move
+#[derive(Default)]
+pub struct DrawOffer {
+    // color of the player who offered the draw
+    pub offered_by: Option<PieceColor>,
+    // number of moves played when the draw was offered
+    moves: usize,
+}
+
+// a game can be aborted until this many moves were played
+pub const ABORT_MOVES: usize = 2;
+
+// position a new game starts from, the standard setup unless a fen was given
+pub struct StartPosition(pub Position);
+
+// number of moves of the move list the board shows while the player looks at an earlier position,
+// None while it shows the live game
+#[derive(Default)]
+pub struct HistoryView {
+    pub ply: Option<usize>,
+}
+
+// the position the pieces show, the live position or an earlier one while viewing the history
+pub struct DisplayedPosition(pub Position);
+
+#[derive(Default)]
+pub struct BoardPlugin {
+    pub start_position: Position,
+    pub move_input: MoveInput,
+}
 
 impl Plugin for BoardPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.init_resource::<SelectedSquare>().init_resource::<SelectedPiece>().init_resource::<PlayerTurn>().add_startup_system(create_board.system()).add_system(color_squares.system()).add_system(select_square.system());
+        app.init_resource::<BoardTheme>().init_resource::<MarkerMeshes>().add_resource(self.move_input).init_resource::<DraggedPiece>()
+        .add_resource(StartPosition(self.start_position.clone())).add_resource(self.start_position.clone()).add_resource(DisplayedPosition(self.start_position.clone()))
+        .init_resource::<HistoryView>().init_resource::<Takeback>().init_resource::<DrawOffer>()
+        .init_resource::<SelectedSquare>().init_resource::<SelectedPiece>().init_resource::<PlayerTurn>().init_resource::<GameResult>().init_resource::<Promotion>().init_resource::<MoveList>()
+        .add_event::<PromotionChoice>().add_event::<NewGame>().add_event::<ClaimDraw>().add_event::<UndoMove>().add_event::<RedoMove>().add_event::<TakebackAnswer>()
+        .add_event::<Resign>().add_event::<OfferDraw>().add_event::<DrawAnswer>().add_event::<Abort>()
+        .add_startup_system(create_board.system()).add_system(color_squares.system()).add_system(show_move_markers.system()).add_system(select_square.system()).add_system(cancel_selection.system()).add_system(drag_piece.system()).add_system(drop_piece.system()).add_system(promote_pawn.system())
+        .add_system(claim_draw.system()).add_system(new_game.system())
+        .add_system(game_action_keys.system()).add_system(resign.system()).add_system(offer_draw.system()).add_system(answer_draw.system()).add_system(withdraw_draw_offer.system()).add_system(abort.system())
+        // after the moves, resignations, draws and flags of the frame, so a move never wipes out a result set in the same frame
+        .add_system_to_stage(stage::POST_UPDATE, update_game_state.system())
+        .add_system(undo_keys.system()).add_system(undo_move.system()).add_system(answer_takeback.system()).add_system(redo_move.system())
+        .add_system(show_live_position.system()).add_system(show_history_position.system());
     }
 }
 
 
-fn create_board(commands: &mut Commands, mut meshes: ResMut<Assets<Mesh>>, mut materials: ResMut<Assets<StandardMaterial>>) {
+fn create_board(commands: &mut Commands, theme: Res<BoardTheme>, mut meshes: ResMut<Assets<Mesh>>, mut materials: ResMut<Assets<StandardMaterial>>) {
     // add meshes and materials
     let mesh = meshes.add(Mesh::from(shape::Plane {size: 1.}));
 
     // spawn 64 squares
     for i in 0..8 {
         for j in 0..8 {
+            let square = Square {x: i, y: j};
             commands.spawn(PbrBundle {
                 mesh: mesh.clone(),
-                material: if (i+j+1) % 2 == 0 {
-                    materials.add(Color::rgb(1., 0.9, 0.9).into())
-                } else {
-                    materials.add(Color::rgb(0., 0.1, 0.1).into())
-                },
+                material: materials.add(if square.is_white() { theme.light_square } else { theme.dark_square }.into()),
                 transform: Transform::from_translation(Vec3::new(i as f32, 0., j as f32)),
                 ..Default::default()
             }).with(PickableMesh::default())
-            .with(Square {x: i, y: j});
+            .with(square);
         }
     }
 }
 
-fn color_squares(pick_state: Res<PickState>, selected_square: Res<SelectedSquare>, mut materials: ResMut<Assets<StandardMaterial>>, query: Query<(Entity, &Square, &Handle<StandardMaterial>)>) {
+// tints the square under the cursor, the selected square, the king in check and the squares of the last move
+fn color_squares(pick_state: Res<PickState>, selected_square: Res<SelectedSquare>, displayed_position: Res<DisplayedPosition>, theme: Res<BoardTheme>, mut materials: ResMut<Assets<StandardMaterial>>, query: Query<(Entity, &Square, &Handle<StandardMaterial>)>) {
     // get entity under the cursor if there is one
     let top_entity = if let Some((entity, _intersection)) = pick_state.top(Group::default()) {
         Some(*entity)
@@ -72,85 +300,683 @@ fn color_squares(pick_state: Res<PickState>, selected_square: Res<SelectedSquare
         None
     };
 
+    let position = &displayed_position.0;
+    let last_move = position.last_move();
+    let check_square = if position.is_check() { position.king_square(position.side_to_move()) } else { None };
+
     for (entity, square, material_handle) in query.iter() {
         // get acutal material
         let material = materials.get_mut(material_handle).unwrap();
+        let board_square = chess_core::Square::new(square.x, square.y);
 
         // change matirial color
         material.albedo = if Some(entity) == top_entity {
-            Color::rgb(0.8, 0.3, 0.3)
+            theme.hovered
         } else if Some(entity) == selected_square.entity {
-            Color::rgb(0.9, 0.1, 0.1)
+            theme.selected
+        } else if Some(board_square) == check_square {
+            theme.check
+        } else if last_move.is_some_and(|mv| mv.from == board_square || mv.to == board_square) {
+            theme.last_move
         } else if square.is_white() {
-            Color::rgb(1., 0.9, 0.9)
+            theme.light_square
         } else {
-            Color::rgb(0., 0.1, 0.1)
+            theme.dark_square
         };
     }
 }
 
-fn select_square(commands: &mut Commands, pick_state: Res<PickState>, mouse_button_inputs: Res<Input<MouseButton>>, mut selected_square: ResMut<SelectedSquare>, mut selected_piece: ResMut<SelectedPiece>, mut turn: ResMut<PlayerTurn>, mut app_exit_events: ResMut<Events<AppExit>>, squares_query: Query<&Square>, mut pieces_query: Query<(Entity, &mut Piece, &Children)>) {
+// shows the legal moves of the selected piece, a dot on an empty square and a ring around a piece it can take
+#[allow(clippy::too_many_arguments)]
+fn show_move_markers(commands: &mut Commands, selected_piece: Res<SelectedPiece>, position: Res<Position>, theme: Res<BoardTheme>, marker_meshes: Res<MarkerMeshes>, mut materials: ResMut<Assets<StandardMaterial>>, pieces_query: Query<&Piece>, markers_query: Query<Entity, With<MoveMarker>>, mut shown: Local<Option<(Entity, u64)>>) {
+    // the markers change with the selection and the position
+    let selection = selected_piece.entity.map(|entity| (entity, position.hash()));
+    if *shown == selection {
+        return;
+    }
+    *shown = selection;
+
+    for entity in markers_query.iter() {
+        commands.despawn(entity);
+    }
+    let piece = match selected_piece.entity.and_then(|entity| pieces_query.get(entity).ok()) {
+        Some(piece) => piece,
+        None => return
+    };
+
+    if let Some(material) = materials.get_mut(&marker_meshes.material) {
+        material.albedo = theme.move_marker;
+    }
+    let mut destinations = Vec::new();
+    for mv in position.legal_moves_from(chess_core::Square::new(piece.x, piece.y)) {
+        // the promotions to the different pieces share one marker
+        if destinations.contains(&mv.to) {
+            continue;
+        }
+        destinations.push(mv.to);
+
+        commands.spawn(PbrBundle {
+            mesh: if mv.is_capture() { marker_meshes.ring.clone() } else { marker_meshes.dot.clone() },
+            material: marker_meshes.material.clone(),
+            // just above the square
+            transform: Transform::from_translation(Vec3::new(mv.to.rank() as f32, 0.01, mv.to.file() as f32)),
+            ..Default::default()
+        }).with(MoveMarker);
+    }
+}
+
+// the board is blocked until the player chose the piece for the promotion, after the game ended, during a replay,
+// while the player looks at an earlier position, until the opponent answered a takeback and while the computer thinks
+fn moves_allowed(position: &Position, promotion: &Promotion, game_result: &GameResult, replay: &Replay, history_view: &HistoryView, takeback: &Takeback, players: &Players) -> bool {
+    promotion.pending_move.is_none() && game_result.0.is_none() && !replay.is_active() && history_view.ply.is_none() && takeback.requested_by.is_none()
+        && !players.is_computer(position.side_to_move())
+}
+
+// what a click on the board does with the piece the player selected to move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Click {
+    // the piece of the side to move on the square is selected, instead of the one selected before
+    Select(chess_core::Square),
+    // nothing is selected anymore
+    Deselect,
+    // the selected piece should move to the square, the selection ends even if it can't
+    Move(Move),
+}
+
+// the click on a square, None outside of the board, with the square of the selected piece
+fn click_square(position: &Position, selected: Option<chess_core::Square>, clicked: Option<chess_core::Square>) -> Click {
+    let clicked = match clicked {
+        Some(clicked) => clicked,
+        None => return Click::Deselect
+    };
+    let own_piece = position.piece_at(clicked).is_some_and(|piece| piece.color == position.side_to_move());
+
+    match selected {
+        // clicking the selected piece again puts it down
+        Some(selected) if selected == clicked => Click::Deselect,
+        // another own piece is selected instead, castling is a king move to its square and not onto the rook
+        _ if own_piece => Click::Select(clicked),
+        Some(selected) => Click::Move(Move::new(selected, clicked)),
+        None => Click::Deselect
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn select_square(pick_state: Res<PickState>, mouse_button_inputs: Res<Input<MouseButton>>, move_input: Res<MoveInput>, mut selected_square: ResMut<SelectedSquare>, mut selected_piece: ResMut<SelectedPiece>, mut dragged_piece: ResMut<DraggedPiece>, mut position: ResMut<Position>, mut move_list: ResMut<MoveList>, mut promotion: ResMut<Promotion>, game_result: Res<GameResult>, replay: Res<Replay>, history_view: Res<HistoryView>, takeback: Res<Takeback>, players: Res<Players>, squares_query: Query<&Square>, mut pieces_query: Query<(Entity, &mut Piece)>) {
     // only run if the lef button is pressed
     if !mouse_button_inputs.just_pressed(MouseButton::Left) {
         return;
     }
 
-    // get the square under the cursor and set it as selected
-    if let Some((square_entity, _intersection)) = pick_state.top(Group::default()) {
-        // get the actual square
-        if let Ok(square) = squares_query.get(*square_entity) {
-            // mark it as selected
-            selected_square.entity = Some(*square_entity);
-            if let Some(selected_piece_entity) = selected_piece.entity {
-                let pieces_vec = pieces_query.iter_mut().map(|(_, piece, _)| *piece).collect();
-                let pieces_entity_vec: Vec<(Entity, Piece, Vec<Entity>)> = pieces_query.iter_mut().map(|(entity, piece, children)| (entity, *piece, children.iter().map(|entity| *entity).collect())).collect();
-                // move selected piece to the selected square
-                if let Ok((_piece_entity, mut piece, other_children)) = pieces_query.get_mut(selected_piece_entity) {
-                    if piece.is_move_valid((square.x, square.y), pieces_vec) {
-                        // check if piece of the opposite color exists on selected square and despawn it
-                        for (other_entity, other_piece, other_children) in pieces_entity_vec {
-                            if other_piece.x == square.x && other_piece.y == square.y && other_piece.color != piece.color {
-
-                                // if the king is taken end the game
-                                if other_piece.piece_type == PieceType::King {
-                                    app_exit_events.send(AppExit);
-                                }
-
-                                // despawn piece
-                                commands.despawn(other_entity);
-                                // despawn all children of it
-                                for child in other_children {
-                                    commands.despawn(child);
-                                }
-                            }
-                        }
-                        piece.x = square.x;
-                        piece.y = square.y;
-
-                        // change turn
-                        turn.0 = match turn.0 {
-                            PieceColor::White => PieceColor::Black,
-                            PieceColor::Black => PieceColor::White
-                        }
-                    }
-                }
-                selected_square.entity = None;
-                selected_piece.entity = None;
-            } else {
-                // select the piece in the currently selected square
-                for (piece_entity, piece, _) in pieces_query.iter_mut() {
-                    // select piece only if its the right turn
-                    if piece.x == square.x && piece.y == square.y && piece.color == turn.0 {
-                        // piece_entity is now the entity in the same square
-                        selected_piece.entity = Some(piece_entity);
-                        break;
-                    }
-                }
+    if !moves_allowed(&position, &promotion, &game_result, &replay, &history_view, &takeback, &players) {
+        return;
+    }
+
+    // the square under the cursor, None outside of the board, and the square of the selected piece
+    let clicked = pick_state.top(Group::default()).and_then(|(square_entity, _intersection)| squares_query.get(*square_entity).ok().map(|square| (*square_entity, square)));
+    let selected = selected_piece.entity.and_then(|entity| pieces_query.get_mut(entity).ok()).map(|(_piece_entity, piece)| chess_core::Square::new(piece.x, piece.y));
+
+    match click_square(&position, selected, clicked.map(|(_square_entity, square)| chess_core::Square::new(square.x, square.y))) {
+        Click::Select(_) => {
+            let (square_entity, square) = clicked.expect("a selected square is on the board");
+            selected_square.entity = Some(square_entity);
+            selected_piece.entity = pieces_query.iter_mut().find(|(_piece_entity, piece)| piece.x == square.x && piece.y == square.y).map(|(piece_entity, _piece)| piece_entity);
+            // it follows the cursor until the button is released
+            if move_input.drag() {
+                dragged_piece.entity = selected_piece.entity;
+            }
+        }
+        Click::Deselect => {
+            selected_square.entity = None;
+            selected_piece.entity = None;
+        }
+        Click::Move(_) => {
+            let (_square_entity, square) = clicked.expect("a move goes to a square on the board");
+            if let Some(Ok((_piece_entity, mut piece))) = selected_piece.entity.map(|entity| pieces_query.get_mut(entity)) {
+                move_piece(&mut position, &mut move_list, &mut promotion, &mut piece, square);
             }
+            selected_square.entity = None;
+            selected_piece.entity = None;
         }
+    }
+}
+
+// a right click puts the selected piece down, a dragged one goes straight back to its square
+fn cancel_selection(mouse_button_inputs: Res<Input<MouseButton>>, mut selected_square: ResMut<SelectedSquare>, mut selected_piece: ResMut<SelectedPiece>, mut dragged_piece: ResMut<DraggedPiece>, mut pieces_query: Query<(&Piece, &mut Transform)>) {
+    if !mouse_button_inputs.just_pressed(MouseButton::Right) {
+        return;
+    }
+
+    if let Some(Ok((piece, mut transform))) = dragged_piece.entity.take().map(|entity| pieces_query.get_mut(entity)) {
+        transform.translation = Vec3::new(piece.x as f32, 0., piece.y as f32);
+    }
+    selected_square.entity = None;
+    selected_piece.entity = None;
+}
+
+// plays the move of the piece to the square if it is legal, a pawn reaching the last row waits there for its promotion,
+// returns false if the piece can't move there
+fn move_piece(position: &mut Position, move_list: &mut MoveList, promotion: &mut Promotion, piece: &mut Piece, square: &Square) -> bool {
+    let mv = Move::new(chess_core::Square::new(piece.x, piece.y), chess_core::Square::new(square.x, square.y));
+
+    if position.is_legal(mv) {
+        // the pieces follow the position in sync_pieces
+        play_move(position, move_list, mv);
+        true
+    } else if position.is_legal(Move::with_promotion(mv.from, mv.to, PieceType::Queen)) {
+        // the pawn waits on the last row until the player chose its new piece
+        piece.x = square.x;
+        piece.y = square.y;
+        promotion.pending_move = Some(mv);
+        true
     } else {
-        // player clicked outside of the board, deselect everything
-        selected_square.entity = None;
-        selected_piece.entity = None;
+        false
+    }
+}
+
+// the dragged piece follows the cursor over the board, a little above it
+fn drag_piece(pick_state: Res<PickState>, dragged_piece: Res<DraggedPiece>, mut query: Query<&mut Transform, With<Piece>>) {
+    let entity = match dragged_piece.entity {
+        Some(entity) => entity,
+        None => return
+    };
+
+    // the ray from the cursor hits the square under it, off the board the piece stays where it was
+    if let (Some((_square_entity, intersection)), Ok(mut transform)) = (pick_state.top(Group::default()), query.get_mut(entity)) {
+        let point = intersection.position();
+        transform.translation = Vec3::new(point.x, DRAG_HEIGHT, point.z);
+    }
+}
+
+// dropping the piece on another square moves it there, an illegal drop puts it back on its square,
+// dropping it where it was picked up keeps it selected for a click on the square it should go to
+#[allow(clippy::too_many_arguments)]
+fn drop_piece(pick_state: Res<PickState>, mouse_button_inputs: Res<Input<MouseButton>>, move_input: Res<MoveInput>, game_result: Res<GameResult>, replay: Res<Replay>, history_view: Res<HistoryView>, takeback: Res<Takeback>, players: Res<Players>, mut dragged_piece: ResMut<DraggedPiece>, mut selected_square: ResMut<SelectedSquare>, mut selected_piece: ResMut<SelectedPiece>, mut position: ResMut<Position>, mut move_list: ResMut<MoveList>, mut promotion: ResMut<Promotion>, squares_query: Query<&Square>, mut pieces_query: Query<(&mut Piece, &mut Transform)>) {
+    if dragged_piece.entity.is_none() || !mouse_button_inputs.just_released(MouseButton::Left) {
+        return;
+    }
+    let entity = dragged_piece.entity.take().expect("checked above");
+
+    // the piece was taken back or the game changed while it was dragged
+    let (mut piece, mut transform) = match pieces_query.get_mut(entity) {
+        Ok(piece) => piece,
+        Err(_) => return
+    };
+
+    // an undo, a takeback request or the end of the game while the piece was dragged puts it back
+    let square = pick_state.top(Group::default()).and_then(|(square_entity, _intersection)| squares_query.get(*square_entity).ok())
+        .filter(|_| moves_allowed(&position, &promotion, &game_result, &replay, &history_view, &takeback, &players));
+    let dropped_on_start = square.is_some_and(|square| square.x == piece.x && square.y == piece.y);
+    if dropped_on_start && move_input.click() {
+        transform.translation = Vec3::new(piece.x as f32, 0., piece.y as f32);
+        return;
+    }
+
+    let moved = match square {
+        Some(square) if !dropped_on_start => move_piece(&mut position, &mut move_list, &mut promotion, &mut piece, square),
+        _ => false
+    };
+    if !moved {
+        // snaps back instead of sliding there in move_pieces
+        transform.translation = Vec3::new(piece.x as f32, 0., piece.y as f32);
+    }
+    selected_square.entity = None;
+    selected_piece.entity = None;
+}
+
+fn promote_pawn(mut choice_reader: Local<EventReader<PromotionChoice>>, choice_events: Res<Events<PromotionChoice>>, mut promotion: ResMut<Promotion>, mut position: ResMut<Position>, mut move_list: ResMut<MoveList>) {
+    for choice in choice_reader.iter(&choice_events) {
+        if let Some(pending_move) = promotion.pending_move {
+            let mv = Move::with_promotion(pending_move.from, pending_move.to, choice.0);
+            if position.is_legal(mv) {
+                play_move(&mut position, &mut move_list, mv);
+            }
+            promotion.pending_move = None;
+        }
+    }
+}
+
+// plays a legal move and records it in the move list
+pub fn play_move(position: &mut Position, move_list: &mut MoveList, mv: Move) {
+    let san = position.san(mv);
+    position.make_move(mv);
+    move_list.moves.push(RecordedMove { mv, san, clock: None });
+    // a new move replaces the moves that were taken back
+    move_list.undone.clear();
+}
+
+// takes back the last move, the pieces and the player turn follow the position
+fn take_back_move(position: &mut Position, move_list: &mut MoveList) {
+    if let Some(recorded) = move_list.moves.pop() {
+        position.unmake_move();
+        move_list.undone.push(recorded);
+    }
+}
+
+// plays the last move that was taken back again
+fn redo_undone_move(position: &mut Position, move_list: &mut MoveList) {
+    if let Some(recorded) = move_list.undone.pop() {
+        position.make_move(recorded.mv);
+        move_list.moves.push(recorded);
+    }
+}
+
+// returns true if the computer plays the side to move and a person the other side
+fn is_computer_reply(position: &Position, players: &Players) -> bool {
+    players.is_computer(position.side_to_move()) && !players.is_computer(position.side_to_move().opposite())
+}
+
+// ctrl+z takes back the last move, ctrl+y or ctrl+shift+z plays it again
+fn undo_keys(keyboard_input: Res<Input<KeyCode>>, mut undo_events: ResMut<Events<UndoMove>>, mut redo_events: ResMut<Events<RedoMove>>) {
+    let ctrl = keyboard_input.pressed(KeyCode::LControl) || keyboard_input.pressed(KeyCode::RControl);
+    let shift = keyboard_input.pressed(KeyCode::LShift) || keyboard_input.pressed(KeyCode::RShift);
+    if !ctrl {
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Y) || (shift && keyboard_input.just_pressed(KeyCode::Z)) {
+        redo_events.send(RedoMove);
+    } else if keyboard_input.just_pressed(KeyCode::Z) {
+        undo_events.send(UndoMove);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn undo_move(mut undo_reader: Local<EventReader<UndoMove>>, undo_events: Res<Events<UndoMove>>, players: Res<Players>, mut position: ResMut<Position>, mut move_list: ResMut<MoveList>, game_result: Res<GameResult>, replay: Res<Replay>, history_view: Res<HistoryView>, mut promotion: ResMut<Promotion>, mut takeback: ResMut<Takeback>, mut displayed_position: ResMut<DisplayedPosition>, mut selected_square: ResMut<SelectedSquare>, mut selected_piece: ResMut<SelectedPiece>) {
+    if undo_reader.iter(&undo_events).next().is_none() {
+        return;
+    }
+
+    // a pawn waiting for its promotion hasn't moved yet, it goes back to its square in sync_pieces
+    if promotion.pending_move.is_some() {
+        promotion.pending_move = None;
+        displayed_position.0 = position.clone();
+        return;
+    }
+
+    if move_list.moves.is_empty() || game_result.0.is_some() || replay.is_active() || history_view.ply.is_some() || takeback.requested_by.is_some() {
+        return;
+    }
+
+    // against the computer the player takes back their move and the reply to it, without asking
+    if players.has_computer() {
+        take_back_move(&mut position, &mut move_list);
+        if is_computer_reply(&position, &players) {
+            take_back_move(&mut position, &mut move_list);
+        }
+        return;
+    }
+
+    // both players sit at the board, the opponent answers the request in the ui
+    takeback.requested_by = Some(position.side_to_move().opposite());
+    selected_square.entity = None;
+    selected_piece.entity = None;
+}
+
+fn answer_takeback(mut answer_reader: Local<EventReader<TakebackAnswer>>, answer_events: Res<Events<TakebackAnswer>>, mut takeback: ResMut<Takeback>, mut position: ResMut<Position>, mut move_list: ResMut<MoveList>) {
+    for answer in answer_reader.iter(&answer_events) {
+        if takeback.requested_by.take().is_some() && answer.0 {
+            take_back_move(&mut position, &mut move_list);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn redo_move(mut redo_reader: Local<EventReader<RedoMove>>, redo_events: Res<Events<RedoMove>>, promotion: Res<Promotion>, game_result: Res<GameResult>, replay: Res<Replay>, history_view: Res<HistoryView>, takeback: Res<Takeback>, players: Res<Players>, mut position: ResMut<Position>, mut move_list: ResMut<MoveList>) {
+    if redo_reader.iter(&redo_events).next().is_none() {
+        return;
+    }
+
+    if promotion.pending_move.is_some() || game_result.0.is_some() || replay.is_active() || history_view.ply.is_some() || takeback.requested_by.is_some() {
+        return;
+    }
+
+    // the reply of the computer comes back together with the move
+    redo_undone_move(&mut position, &mut move_list);
+    if is_computer_reply(&position, &players) {
+        redo_undone_move(&mut position, &mut move_list);
+    }
+}
+
+fn claim_draw(mut claim_reader: Local<EventReader<ClaimDraw>>, claim_events: Res<Events<ClaimDraw>>, position: Res<Position>, promotion: Res<Promotion>, mut game_result: ResMut<GameResult>) {
+    for _claim in claim_reader.iter(&claim_events) {
+        // a draw can only be claimed by the player to move, not while a promotion is open
+        if game_result.0.is_none() && promotion.pending_move.is_none() {
+            game_result.0 = position.claimable_draw();
+        }
+    }
+}
+
+// ctrl+r resigns, ctrl+d offers a draw and ctrl+x aborts, y and n answer a draw offer
+fn game_action_keys(keyboard_input: Res<Input<KeyCode>>, draw_offer: Res<DrawOffer>, mut resign_events: ResMut<Events<Resign>>, mut offer_events: ResMut<Events<OfferDraw>>, mut answer_events: ResMut<Events<DrawAnswer>>, mut abort_events: ResMut<Events<Abort>>) {
+    let ctrl = keyboard_input.pressed(KeyCode::LControl) || keyboard_input.pressed(KeyCode::RControl);
+    if !ctrl {
+        if draw_offer.offered_by.is_some() && keyboard_input.just_pressed(KeyCode::Y) {
+            answer_events.send(DrawAnswer(true));
+        } else if draw_offer.offered_by.is_some() && keyboard_input.just_pressed(KeyCode::N) {
+            answer_events.send(DrawAnswer(false));
+        }
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::R) {
+        resign_events.send(Resign);
+    } else if keyboard_input.just_pressed(KeyCode::D) {
+        offer_events.send(OfferDraw);
+    } else if keyboard_input.just_pressed(KeyCode::X) {
+        abort_events.send(Abort);
+    }
+}
+
+// the person resigns against the computer, between two people the player to move does
+fn resign(mut resign_reader: Local<EventReader<Resign>>, resign_events: Res<Events<Resign>>, position: Res<Position>, players: Res<Players>, replay: Res<Replay>, mut game_result: ResMut<GameResult>) {
+    if resign_reader.iter(&resign_events).next().is_none() || game_result.0.is_some() || replay.is_active() {
+        return;
+    }
+
+    if let Some(color) = players.person(position.side_to_move()) {
+        game_result.0 = Some(GameEnd::Resignation(color.opposite()));
+    }
+}
+
+// the computer answers a draw offer right away, a person answers it in the ui
+#[allow(clippy::too_many_arguments)]
+fn offer_draw(mut offer_reader: Local<EventReader<OfferDraw>>, offer_events: Res<Events<OfferDraw>>, position: Res<Position>, move_list: Res<MoveList>, players: Res<Players>, replay: Res<Replay>, mut draw_offer: ResMut<DrawOffer>, mut game_result: ResMut<GameResult>) {
+    if offer_reader.iter(&offer_events).next().is_none() || game_result.0.is_some() || replay.is_active() || draw_offer.offered_by.is_some() {
+        return;
+    }
+
+    let color = match players.person(position.side_to_move()) {
+        Some(color) => color,
+        None => return
     };
-}
\ No newline at end of file
+    if players.is_computer(color.opposite()) {
+        if accepts_draw(&position, color.opposite()) {
+            game_result.0 = Some(GameEnd::DrawAgreement);
+        }
+        return;
+    }
+    *draw_offer = DrawOffer { offered_by: Some(color), moves: move_list.moves.len() };
+}
+
+fn answer_draw(mut answer_reader: Local<EventReader<DrawAnswer>>, answer_events: Res<Events<DrawAnswer>>, mut draw_offer: ResMut<DrawOffer>, mut game_result: ResMut<GameResult>) {
+    for answer in answer_reader.iter(&answer_events) {
+        if draw_offer.offered_by.take().is_some() && answer.0 && game_result.0.is_none() {
+            game_result.0 = Some(GameEnd::DrawAgreement);
+        }
+    }
+}
+
+// the offer stands while the player who offered makes their move, the move of the opponent declines it
+// and a takeback withdraws it
+fn withdraw_draw_offer(move_list: ChangedRes<MoveList>, position: Res<Position>, mut draw_offer: ResMut<DrawOffer>) {
+    if let Some(offered_by) = draw_offer.offered_by {
+        let moves = move_list.moves.len();
+        if moves < draw_offer.moves || (moves > draw_offer.moves && position.side_to_move() == offered_by) {
+            draw_offer.offered_by = None;
+        }
+    }
+}
+
+// a game can be called off before both players made a move, nobody wins it
+fn abort(mut abort_reader: Local<EventReader<Abort>>, abort_events: Res<Events<Abort>>, move_list: Res<MoveList>, replay: Res<Replay>, mut game_result: ResMut<GameResult>) {
+    if abort_reader.iter(&abort_events).next().is_none() || game_result.0.is_some() || replay.is_active() {
+        return;
+    }
+
+    if move_list.moves.len() < ABORT_MOVES {
+        game_result.0 = Some(GameEnd::Aborted);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn new_game(mut new_game_reader: Local<EventReader<NewGame>>, new_game_events: Res<Events<NewGame>>, start_position: Res<StartPosition>, mut selected_square: ResMut<SelectedSquare>, mut selected_piece: ResMut<SelectedPiece>, mut position: ResMut<Position>, mut move_list: ResMut<MoveList>, mut history_view: ResMut<HistoryView>, mut game_result: ResMut<GameResult>, mut promotion: ResMut<Promotion>, mut takeback: ResMut<Takeback>, mut draw_offer: ResMut<DrawOffer>) {
+    if new_game_reader.iter(&new_game_events).next().is_none() {
+        return;
+    }
+
+    // the pieces go back to their squares in sync_pieces
+    *position = start_position.0.clone();
+    *move_list = MoveList::default();
+    *history_view = HistoryView::default();
+
+    selected_square.entity = None;
+    selected_piece.entity = None;
+    *game_result = GameResult::default();
+    *promotion = Promotion::default();
+    *takeback = Takeback::default();
+    *draw_offer = DrawOffer::default();
+}
+
+// looks at the position after every move, the game ends when the rules say so, a replay never ends,
+// a result set by a resignation, a draw agreement or the clock stays until the next game
+fn update_game_state(position: ChangedRes<Position>, replay: Res<Replay>, mut turn: ResMut<PlayerTurn>, mut game_result: ResMut<GameResult>) {
+    turn.0 = position.side_to_move();
+    if game_result.0.is_none() && !replay.is_active() {
+        game_result.0 = position.game_end();
+    }
+}
+
+// the board follows the game unless the player looks at an earlier position
+fn show_live_position(position: ChangedRes<Position>, history_view: Res<HistoryView>, mut displayed_position: ResMut<DisplayedPosition>) {
+    if history_view.ply.is_none() {
+        displayed_position.0 = position.clone();
+    }
+}
+
+fn show_history_position(history_view: ChangedRes<HistoryView>, position: Res<Position>, start_position: Res<StartPosition>, move_list: Res<MoveList>, mut displayed_position: ResMut<DisplayedPosition>) {
+    displayed_position.0 = match history_view.ply {
+        Some(ply) => {
+            let mut history_position = start_position.0.clone();
+            for recorded in move_list.moves.iter().take(ply) {
+                history_position.make_move(recorded.mv);
+            }
+            history_position
+        }
+        None => position.clone()
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use bevy::{asset::AssetPlugin, core::CorePlugin, reflect::ReflectPlugin, window::{CursorMoved, Windows}};
+
+    use super::*;
+
+    // the board of the position with its squares, pieces and a pick source above it that stands in for the cursor
+    fn board(fen: &str, move_input: MoveInput) -> App {
+        let position = Position::from_fen(fen).unwrap();
+        let mut builder = App::build();
+        builder.add_plugin(ReflectPlugin).add_plugin(CorePlugin).add_plugin(AssetPlugin).add_asset::<Mesh>()
+        .init_resource::<Windows>().add_event::<CursorMoved>().init_resource::<Input<MouseButton>>()
+        .add_plugin(PickingPlugin)
+        .add_resource(move_input).add_resource(position.clone()).init_resource::<MoveList>().init_resource::<Promotion>().init_resource::<GameResult>()
+        .init_resource::<Replay>().init_resource::<HistoryView>().init_resource::<Takeback>().init_resource::<Players>()
+        .init_resource::<SelectedSquare>().init_resource::<SelectedPiece>().init_resource::<DraggedPiece>()
+        // the picks of the frame are there before the board reads them
+        .add_stage_after(stage::UPDATE, "board", SystemStage::serial())
+        .add_system_to_stage("board", select_square.system()).add_system_to_stage("board", cancel_selection.system())
+        .add_system_to_stage("board", drag_piece.system()).add_system_to_stage("board", drop_piece.system());
+        let mut app = builder.app;
+
+        let mesh = app.resources.get_mut::<Assets<Mesh>>().unwrap().add(Mesh::from(shape::Plane {size: 1.}));
+        for x in 0..8 {
+            for y in 0..8 {
+                let translation = Vec3::new(x as f32, 0., y as f32);
+                app.world.spawn((Square {x, y}, mesh.clone(), GlobalTransform::from_translation(translation), Visible::default(), PickableMesh::default()));
+                if let Some(piece) = position.piece_at(chess_core::Square::new(x, y)) {
+                    app.world.spawn((Piece {color: piece.color, piece_type: piece.piece_type, x, y}, Transform::from_translation(translation)));
+                }
+            }
+        }
+        app.world.spawn((PickSource::new(vec![Group::default()], PickMethod::Transform), GlobalTransform::default()));
+        app.update();
+        app
+    }
+
+    // "e2" is rank 1 and file 4, the x and y of the board
+    fn coordinates(name: &str) -> (u8, u8) {
+        let bytes = name.as_bytes();
+        (bytes[1] - b'1', bytes[0] - b'a')
+    }
+
+    // the pick ray comes straight down on the square, None points it away from the board
+    fn point_at(app: &mut App, name: Option<&str>) {
+        let transform = match name.map(coordinates) {
+            Some((x, y)) => GlobalTransform::from_translation(Vec3::new(x as f32, 5., y as f32)).mul_transform(Transform::from_rotation(Quat::from_rotation_x(FRAC_PI_2))),
+            None => GlobalTransform::from_translation(Vec3::new(0., 5., 0.)).mul_transform(Transform::from_rotation(Quat::from_rotation_x(-FRAC_PI_2)))
+        };
+        for (_source, mut global_transform) in app.world.query_mut::<(&PickSource, &mut GlobalTransform)>() {
+            *global_transform = transform;
+        }
+        app.update();
+    }
+
+    fn press(app: &mut App, button: MouseButton) {
+        app.resources.get_mut::<Input<MouseButton>>().unwrap().press(button);
+        app.update();
+        app.resources.get_mut::<Input<MouseButton>>().unwrap().update();
+    }
+
+    fn release(app: &mut App, button: MouseButton) {
+        app.resources.get_mut::<Input<MouseButton>>().unwrap().release(button);
+        app.update();
+        app.resources.get_mut::<Input<MouseButton>>().unwrap().update();
+    }
+
+    fn click(app: &mut App, name: &str) {
+        point_at(app, Some(name));
+        press(app, MouseButton::Left);
+        release(app, MouseButton::Left);
+    }
+
+    // board coordinates of the selected piece
+    fn selected(app: &App) -> Option<(u8, u8)> {
+        let entity = app.resources.get::<SelectedPiece>().unwrap().entity?;
+        let piece = app.world.get::<Piece>(entity).unwrap();
+        Some((piece.x, piece.y))
+    }
+
+    fn piece_at(app: &mut App, name: &str) -> Entity {
+        let (x, y) = coordinates(name);
+        app.world.query::<(Entity, &Piece)>().find(|(_entity, piece)| piece.x == x && piece.y == y).unwrap().0
+    }
+
+    #[test]
+    fn switch_and_deselect() {
+        for move_input in [MoveInput::ClickClick, MoveInput::Both].iter() {
+            let mut app = board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", *move_input);
+            click(&mut app, "e2");
+            assert_eq!(selected(&app), Some(coordinates("e2")));
+
+            // another own piece takes over the selection without a wasted click
+            click(&mut app, "g1");
+            assert_eq!(selected(&app), Some(coordinates("g1")));
+            assert!(app.resources.get::<MoveList>().unwrap().moves.is_empty());
+
+            // the selected piece again puts it down
+            click(&mut app, "g1");
+            assert_eq!(selected(&app), None);
+
+            // so does a click outside of the board
+            click(&mut app, "d2");
+            point_at(&mut app, None);
+            press(&mut app, MouseButton::Left);
+            assert_eq!(selected(&app), None);
+        }
+    }
+
+    #[test]
+    fn right_click_cancels() {
+        let mut app = board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", MoveInput::Both);
+        click(&mut app, "e2");
+        press(&mut app, MouseButton::Right);
+        release(&mut app, MouseButton::Right);
+        assert_eq!(selected(&app), None);
+        assert!(app.resources.get::<SelectedSquare>().unwrap().entity.is_none());
+
+        // a piece dragged away goes straight back to its square
+        let pawn = piece_at(&mut app, "d2");
+        point_at(&mut app, Some("d2"));
+        press(&mut app, MouseButton::Left);
+        point_at(&mut app, Some("d5"));
+        assert_eq!(app.world.get::<Transform>(pawn).unwrap().translation, Vec3::new(4., DRAG_HEIGHT, 3.));
+        press(&mut app, MouseButton::Right);
+        assert_eq!(selected(&app), None);
+        assert!(app.resources.get::<DraggedPiece>().unwrap().entity.is_none());
+        assert_eq!(app.world.get::<Transform>(pawn).unwrap().translation, Vec3::new(1., 0., 3.));
+
+        // releasing the button afterwards doesn't drop it anywhere
+        release(&mut app, MouseButton::Left);
+        assert!(app.resources.get::<MoveList>().unwrap().moves.is_empty());
+    }
+
+    #[test]
+    fn moves() {
+        let mut app = board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", MoveInput::ClickClick);
+        // an illegal target only ends the selection
+        click(&mut app, "e2");
+        click(&mut app, "e5");
+        assert_eq!(selected(&app), None);
+        assert!(app.resources.get::<MoveList>().unwrap().moves.is_empty());
+
+        click(&mut app, "e2");
+        click(&mut app, "e4");
+        assert_eq!(selected(&app), None);
+        assert_eq!(app.resources.get::<MoveList>().unwrap().moves[0].san, "e4");
+        assert_eq!(app.resources.get::<Position>().unwrap().side_to_move(), PieceColor::Black);
+
+        // the same with drag and drop, an illegal drop snaps back
+        let mut app = board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", MoveInput::DragAndDrop);
+        let knight = piece_at(&mut app, "g1");
+        point_at(&mut app, Some("g1"));
+        press(&mut app, MouseButton::Left);
+        point_at(&mut app, Some("g3"));
+        release(&mut app, MouseButton::Left);
+        assert_eq!(app.world.get::<Transform>(knight).unwrap().translation, Vec3::new(0., 0., 6.));
+        assert!(app.resources.get::<MoveList>().unwrap().moves.is_empty());
+
+        point_at(&mut app, Some("g1"));
+        press(&mut app, MouseButton::Left);
+        point_at(&mut app, Some("f3"));
+        release(&mut app, MouseButton::Left);
+        assert_eq!(app.resources.get::<MoveList>().unwrap().moves[0].san, "Nf3");
+        assert_eq!(selected(&app), None);
+    }
+
+    #[test]
+    fn blocked_drop() {
+        // a takeback request while the piece is dragged puts it back on its square
+        let mut app = board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", MoveInput::DragAndDrop);
+        let pawn = piece_at(&mut app, "e2");
+        point_at(&mut app, Some("e2"));
+        press(&mut app, MouseButton::Left);
+        point_at(&mut app, Some("e4"));
+        app.resources.get_mut::<Takeback>().unwrap().requested_by = Some(PieceColor::Black);
+        release(&mut app, MouseButton::Left);
+        assert!(app.resources.get::<MoveList>().unwrap().moves.is_empty());
+        assert_eq!(app.world.get::<Transform>(pawn).unwrap().translation, Vec3::new(1., 0., 4.));
+        assert_eq!(selected(&app), None);
+    }
+
+    #[test]
+    fn result_survives_a_move() {
+        // the computer answers in the same frame as the player resigns
+        let mut builder = App::build();
+        builder.add_resource(Position::from_fen("7k/8/8/8/8/8/8/KQ6 w - - 0 1").unwrap()).init_resource::<Replay>().init_resource::<PlayerTurn>().init_resource::<GameResult>()
+        .add_system_to_stage(stage::POST_UPDATE, update_game_state.system());
+        let mut app = builder.app;
+        app.update();
+
+        app.resources.get_mut::<GameResult>().unwrap().0 = Some(GameEnd::Resignation(PieceColor::Black));
+        let mut position = app.resources.get_mut::<Position>().unwrap();
+        let mv = position.parse_uci("b1b7").unwrap();
+        position.make_move(mv);
+        drop(position);
+        app.update();
+        assert_eq!(app.resources.get::<GameResult>().unwrap().0, Some(GameEnd::Resignation(PieceColor::Black)));
+        assert_eq!(app.resources.get::<PlayerTurn>().unwrap().0, PieceColor::Black);
+    }
+}
@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+
+use chess_core::{Clock, Position, TimeControl};
+
+use crate::{board::*, replay::Replay};
+
+// the clocks of the game, a game without a time control has none
+#[derive(Default)]
+pub struct GameClock {
+    // time control of the next game, chosen in the new game dialog
+    pub control: Option<TimeControl>,
+    pub clock: Option<Clock>,
+    // the clock before every move of the move list, a takeback gives the time back
+    history: Vec<Clock>,
+}
+
+impl GameClock {
+    // both clocks start again with the time control, the clock of the player to move runs
+    fn reset(&mut self, start: &Position) {
+        self.clock = self.control.clone().map(|control| Clock::new(control, start.side_to_move()));
+        self.history.clear();
+    }
+
+    // the clocks start with the first move of the game
+    pub fn started(&self) -> bool {
+        !self.history.is_empty()
+    }
+}
+
+#[derive(Default)]
+pub struct ClockPlugin {
+    pub control: Option<TimeControl>,
+}
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_resource(GameClock { control: self.control.clone(), ..Default::default() })
+        .add_startup_system(start_clock.system()).add_system(new_game_clock.system())
+        .add_system(press_clock.system()).add_system(run_clock.system());
+    }
+}
+
+fn start_clock(start_position: Res<StartPosition>, mut game_clock: ResMut<GameClock>) {
+    game_clock.reset(&start_position.0);
+}
+
+fn new_game_clock(mut new_game_reader: Local<EventReader<NewGame>>, new_game_events: Res<Events<NewGame>>, start_position: Res<StartPosition>, mut game_clock: ResMut<GameClock>) {
+    if new_game_reader.iter(&new_game_events).next().is_some() {
+        game_clock.reset(&start_position.0);
+    }
+}
+
+// presses the clock for every new move and records the time the player has left with it,
+// a takeback sets the clock back to where it was before the move
+fn press_clock(mut game_clock: ResMut<GameClock>, mut move_list: ResMut<MoveList>) {
+    let played = move_list.moves.len();
+    if game_clock.clock.is_none() || game_clock.history.len() == played {
+        return;
+    }
+
+    let GameClock { clock, history, .. } = &mut *game_clock;
+    let clock = clock.as_mut().expect("checked above");
+    if played < history.len() {
+        *clock = history[played].clone();
+        history.truncate(played);
+    }
+    while history.len() < played {
+        history.push(clock.clone());
+        let color = clock.turn();
+        clock.press();
+        move_list.moves[history.len() - 1].clock = Some(clock.remaining(color));
+    }
+}
+
+// the clock of the player to move runs while the game goes on, the game ends when it runs out,
+// it starts with the first move and stands still while the player chooses the piece for a promotion
+fn run_clock(time: Res<Time>, position: Res<Position>, replay: Res<Replay>, promotion: Res<Promotion>, mut game_clock: ResMut<GameClock>, mut game_result: ResMut<GameResult>) {
+    if game_result.0.is_some() || replay.is_active() || promotion.pending_move.is_some() || !game_clock.started() {
+        return;
+    }
+
+    if let Some(clock) = &mut game_clock.clock {
+        clock.tick(time.delta());
+        if let Some(flagged) = clock.flagged() {
+            game_result.0 = Some(position.timeout(flagged));
+        }
+    }
+}
@@ -1,6 +1,10 @@
+use std::path::PathBuf;
+
 use bevy::prelude::*;
 use bevy_mod_picking::*;
 
+use chess_core::{read_pgn, PgnGame, Position, TimeControl};
+
 mod pieces;
 use pieces::*;
 
@@ -10,19 +14,147 @@ use board::*;
 mod ui;
 use ui::*;
 
+mod pgn;
+use pgn::*;
+
+mod replay;
+use replay::*;
+
+mod ai;
+use ai::*;
+
+mod clock;
+use clock::*;
+
 fn main() {
+    let start_position = start_position();
+    let replay_games = replay_games();
+    let engines = Engines::find(arg_value("--engine").map(PathBuf::from).into_iter().collect());
+    let players = players(&engines);
+    let analysis = arg_value("--analyze").map(|name| engine_index(&engines, &name));
+    let time_control = time_control();
+    let move_input = move_input();
+    let pgn_directory = arg_value("--pgn-dir").map(PathBuf::from).unwrap_or_else(|| PgnPlugin::default().directory);
+
     App::build().add_resource(Msaa {samples: 4})
     .add_resource(WindowDescriptor {title: "Chess".to_string(), width: 1200., height: 800., ..Default::default()})
     .add_plugins(DefaultPlugins)
     .add_plugin(PickingPlugin)
-    .add_plugin(BoardPlugin)
+    .add_plugin(BoardPlugin {start_position, move_input})
+    // after the board, the replay sets the position to its first game
+    .add_plugin(ReplayPlugin {games: replay_games})
     .add_plugin(PiecesPlugin)
     .add_plugin(UIPlugin)
+    .add_plugin(PgnPlugin {directory: pgn_directory})
+    .add_plugin(ClockPlugin {control: time_control})
+    .add_plugin(AiPlugin {players, engines, analysis})
     .add_startup_system(setup.system())
     .run();
 }
 
-fn setup(commands: &mut Commands, mut meshes: ResMut<Assets<Mesh>>, mut materials: ResMut<Assets<StandardMaterial>>) {
+// the value after the argument, given as `--name value` or `--name=value`
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return Some(args.next().unwrap_or_else(|| {
+                eprintln!("{} needs a value", name);
+                std::process::exit(2);
+            }));
+        }
+        if let Some(value) = arg.strip_prefix(name).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+// the position from `--fen "<fen>"`, the standard setup without it
+fn start_position() -> Position {
+    match arg_value("--fen") {
+        Some(fen) => Position::from_fen(&fen).unwrap_or_else(|error| {
+            eprintln!("invalid fen \"{}\": {}", fen, error);
+            std::process::exit(2);
+        }),
+        None => Position::default()
+    }
+}
+
+// the time control from `--time <control>` like "5+3" or "40/90+30:30+30", games have no clock without it
+fn time_control() -> Option<TimeControl> {
+    arg_value("--time").map(|text| TimeControl::parse(&text).unwrap_or_else(|error| {
+        eprintln!("invalid time control \"{}\": {}", text, error);
+        std::process::exit(2);
+    }))
+}
+
+// how pieces are moved with the mouse from `--input click|drag|both`, both work without it
+fn move_input() -> MoveInput {
+    match arg_value("--input").as_deref() {
+        Some("click") => MoveInput::ClickClick,
+        Some("drag") => MoveInput::DragAndDrop,
+        Some("both") | None => MoveInput::Both,
+        Some(input) => {
+            eprintln!("--input needs click, drag or both, not \"{}\"", input);
+            std::process::exit(2);
+        }
+    }
+}
+
+// the games from `--pgn <file>` to replay, none without it
+fn replay_games() -> Vec<PgnGame> {
+    let path = match arg_value("--pgn") {
+        Some(path) => path,
+        None => return Vec::new()
+    };
+
+    let games = std::fs::read_to_string(&path).map_err(|error| error.to_string())
+        .and_then(|text| read_pgn(&text).map_err(|error| error.to_string()))
+        .unwrap_or_else(|error| {
+            eprintln!("couldn't read {}: {}", path, error);
+            std::process::exit(2);
+        });
+    if games.is_empty() {
+        eprintln!("{} contains no games", path);
+        std::process::exit(2);
+    }
+    games
+}
+
+// the index of the engine with the name from the command line, `--engine <path>` adds one that isn't found in PATH
+fn engine_index(engines: &Engines, name: &str) -> usize {
+    engines.0.iter().position(|program| program.name == name || program.path.to_str() == Some(name)).unwrap_or_else(|| {
+        let names: Vec<&str> = engines.0.iter().map(|program| program.name.as_str()).collect();
+        eprintln!("no uci engine named \"{}\", found: {}", name, if names.is_empty() { "none".to_string() } else { names.join(", ") });
+        std::process::exit(2);
+    })
+}
+
+// the colors from `--computer white|black|both` are played by the computer, people play each other without it,
+// with `--computer-engine <name>` the computer is that uci engine instead of the built-in search
+fn players(engines: &Engines) -> Players {
+    let mut players = Players::default();
+    let computer = match arg_value("--computer-engine") {
+        Some(name) => Player::Engine(engine_index(engines, &name)),
+        None => Player::Computer
+    };
+    match arg_value("--computer").as_deref() {
+        Some("white") => players.white.player = computer,
+        Some("black") => players.black.player = computer,
+        Some("both") => {
+            players.white.player = computer;
+            players.black.player = computer;
+        }
+        Some(color) => {
+            eprintln!("--computer needs white, black or both, not \"{}\"", color);
+            std::process::exit(2);
+        }
+        None => {}
+    }
+    players
+}
+
+fn setup(commands: &mut Commands) {
     // camera
     commands.spawn(Camera3dBundle {
         transform: Transform::from_matrix(Mat4::from_rotation_translation(Quat::from_xyzw(-0.3, -0.5, -0.3, 0.5).normalize(), Vec3::new(-7., 20., 4.))),
@@ -0,0 +1,96 @@
+use std::{fs, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+
+use bevy::prelude::*;
+
+use chess_core::{clock_comment, PgnGame, PgnResult};
+
+use crate::{board::*, clock::GameClock, replay::Replay};
+
+// where the games are saved
+pub struct PgnConfig {
+    pub directory: PathBuf,
+}
+
+pub struct PgnPlugin {
+    pub directory: PathBuf,
+}
+
+impl Default for PgnPlugin {
+    fn default() -> Self {
+        PgnPlugin { directory: PathBuf::from("games") }
+    }
+}
+
+impl Plugin for PgnPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_resource(PgnConfig { directory: self.directory.clone() })
+        .add_system(save_finished_game.system()).add_system(save_game_shortcut.system());
+    }
+}
+
+// saves the game as soon as it ends
+fn save_finished_game(game_result: ChangedRes<GameResult>, config: Res<PgnConfig>, start_position: Res<StartPosition>, move_list: Res<MoveList>, game_clock: Res<GameClock>) {
+    if game_result.0.is_some() {
+        save_game(&config, &start_position, &move_list, &game_result, &game_clock);
+    }
+}
+
+// ctrl+s saves the game at any time, an unfinished game gets the result "*"
+fn save_game_shortcut(keyboard_input: Res<Input<KeyCode>>, config: Res<PgnConfig>, start_position: Res<StartPosition>, move_list: Res<MoveList>, game_result: Res<GameResult>, game_clock: Res<GameClock>, replay: Res<Replay>) {
+    let ctrl = keyboard_input.pressed(KeyCode::LControl) || keyboard_input.pressed(KeyCode::RControl);
+    if ctrl && keyboard_input.just_pressed(KeyCode::S) && !replay.is_active() {
+        save_game(&config, &start_position, &move_list, &game_result, &game_clock);
+    }
+}
+
+pub fn pgn_game(start_position: &StartPosition, move_list: &MoveList, game_result: &GameResult, game_clock: &GameClock) -> PgnGame {
+    let mut game = PgnGame::new(start_position.0.clone());
+    let (year, month, day, _) = date_time(move_list.started);
+    game.set_tag("Event", "Casual game");
+    game.set_tag("Date", &format!("{:04}.{:02}.{:02}", year, month, day));
+    game.set_tag("Round", "-");
+    if let Some(clock) = &game_clock.clock {
+        game.set_tag("TimeControl", &clock.control().to_pgn());
+    }
+
+    for recorded in move_list.moves.iter() {
+        game.push(recorded.mv, recorded.clock.map(clock_comment));
+    }
+    game.result = PgnResult::from_game_end(game_result.0);
+    if let Some(game_end) = game_result.0 {
+        game.set_tag("Termination", game_end.termination());
+    }
+    game
+}
+
+// every game gets its own file named after the time it started, saving it again overwrites the file
+fn save_game(config: &PgnConfig, start_position: &StartPosition, move_list: &MoveList, game_result: &GameResult, game_clock: &GameClock) {
+    let (year, month, day, seconds) = date_time(move_list.started);
+    let file_name = format!("game-{:04}-{:02}-{:02}-{:02}{:02}{:02}.pgn", year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60);
+    let path = config.directory.join(file_name);
+
+    let pgn = pgn_game(start_position, move_list, game_result, game_clock).to_pgn();
+    match fs::create_dir_all(&config.directory).and_then(|_| fs::write(&path, pgn)) {
+        Ok(()) => info!("saved the game to {}", path.display()),
+        Err(error) => error!("couldn't save the game to {}: {}", path.display(), error),
+    }
+}
+
+// utc year, month, day and seconds of the day
+fn date_time(time: SystemTime) -> (i64, u32, u32, u64) {
+    let seconds = time.duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0);
+    let days = (seconds / 86_400) as i64;
+
+    // civil date from days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day, seconds % 86_400)
+}
@@ -1,21 +1,11 @@
 use bevy::prelude::*;
 
-#[derive(Clone, Copy, PartialEq)]
-pub enum PieceColor {
-    White,
-    Black,
-}
+pub use chess_core::{PieceColor, PieceType};
+use chess_core::Position;
 
-#[derive(Clone, Copy, PartialEq)]
-pub enum PieceType {
-    King,
-    Queen,
-    Bishop,
-    Knight,
-    Rook,
-    Pawn,
-}
+use crate::board::{DisplayedPosition, DraggedPiece};
 
+// a piece on the board, its entity is kept in sync with the position
 #[derive(Clone, Copy)]
 pub struct Piece {
     pub color: PieceColor,
@@ -25,141 +15,123 @@ pub struct Piece {
     pub y: u8
 }
 
+pub struct PiecesPlugin;
 
-impl Piece {
-    // returns the possible_positions that are available
-    pub fn is_move_valid(&self, new_position: (u8, u8), pieces: Vec<Piece>) -> bool {
-        // if there is a piece of the same color in the same square it can't move
-        if color_of_square(new_position, &pieces) == Some(self.color) {
-            return false;
-        }
+impl Plugin for PiecesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PieceMeshes>().add_startup_system(create_pieces.system()).add_system(sync_pieces.system()).add_system(move_pieces.system());
+    }
+}
 
-        match self.piece_type {
-            PieceType::King => {
-                // horizontal
-                return ((self.x as i8 - new_position.0 as i8).abs() == 1 && (self.y == new_position.1))
-                    // vertical
-                    || ((self.y as i8 - new_position.1 as i8).abs() == 1 && (self.x == new_position.0))
-                    // diagonal
-                    || ((self.x as i8 - new_position.0 as i8).abs() == 1 && (self.y as i8 - new_position.1 as i8).abs() == 1);
-            }
-            PieceType::Queen => {
-                return is_path_empty((self.x, self.y), new_position, &pieces)
-                && ((self.x as i8 - new_position.0 as i8).abs() == (self.y as i8 - new_position.1 as i8).abs()
-                    || ((self.x == new_position.0 && self.y != new_position.1)
-                        || (self.y == new_position.1 && self.x != new_position.0)));
-            }
-            PieceType::Bishop => {
-                return is_path_empty((self.x, self.y), new_position, &pieces)
-                    && (self.x as i8 - new_position.0 as i8).abs() == (self.y as i8 -new_position.1 as i8).abs();
-            }
-            PieceType::Knight => {
-                return ((self.x as i8 - new_position.0 as i8).abs() == 2 && (self.y as i8 - new_position.1 as i8).abs() == 1)
-                    || ((self.x as i8 - new_position.0 as i8).abs() == 1 && (self.y as i8 - new_position.1 as i8).abs() == 2);
-            }
-            PieceType::Rook => {
-                return is_path_empty((self.x, self.y), new_position, &pieces)
-                    && ((self.x == new_position.0 && self.y != new_position.1)
-                        || (self.y == new_position.1 && self.x != new_position.0));
-            }
-            PieceType::Pawn => {
-                if self.color == PieceColor::White {
-                    // normal move
-                    if new_position.0 as i8 - self.x as i8 == 1 && (self.y == new_position.1) {
-                        if color_of_square(new_position, &pieces).is_none() {
-                            return true;
-                        }
-                    }
-                    // move 2 squares
-                    if self.x == 1 && new_position.0 as i8 - self.x as i8 == 2
-                    && (self.y == new_position.1) && is_path_empty((self.x, self.y), new_position, &pieces) {
-                        if color_of_square(new_position, &pieces).is_none() {
-                            return true;
-                        }
-                    }
+// meshes and materials of the pieces, kept around to spawn pieces during the game
+pub struct PieceMeshes {
+    king: Handle<Mesh>,
+    king_cross: Handle<Mesh>,
+    pawn: Handle<Mesh>,
+    knight_1: Handle<Mesh>,
+    knight_2: Handle<Mesh>,
+    rook: Handle<Mesh>,
+    bishop: Handle<Mesh>,
+    queen: Handle<Mesh>,
+    white_material: Handle<StandardMaterial>,
+    black_material: Handle<StandardMaterial>,
+}
 
-                    // take piece
-                    if new_position.0 as i8 - self.x as i8 == 1 && (self.y as i8 - new_position.1 as i8).abs() == 1 {
-                        if color_of_square(new_position, &pieces) == Some(PieceColor::Black) {
-                            return true;
-                        }
-                    }
-                } else {
-                    // normal move
-                    if new_position.0 as i8 - self.x as i8 == -1 && (self.y == new_position.1) {
-                        if color_of_square(new_position, &pieces).is_none() {
-                            return true;
-                        }
-                    }
-                    // move 2 squares
-                    if self.x == 6 && new_position.0 as i8 - self.x as i8 == -2
-                    && (self.y == new_position.1) && is_path_empty((self.x, self.y), new_position, &pieces) {
-                        if color_of_square(new_position, &pieces).is_none() {
-                            return true;
-                        }
-                    }  
+impl FromResources for PieceMeshes {
+    fn from_resources(resources: &Resources) -> Self {
+        let asset_server = resources.get::<AssetServer>().unwrap();
+        let mut materials = resources.get_mut::<Assets<StandardMaterial>>().unwrap();
 
-                    // take piece
-                    if new_position.0 as i8 - self.x as i8 == -1 && (self.y as i8 - new_position.1 as i8).abs() == 1 {
-                        if color_of_square(new_position, &pieces) == Some(PieceColor::White) {
-                            return true;
-                        }
-                    }
-                }
-            }
+        PieceMeshes {
+            king: asset_server.load("models/chess_kit/pieces.glb#Mesh0/Primitive0"),
+            king_cross: asset_server.load("models/chess_kit/pieces.glb#Mesh1/Primitive0"),
+            pawn: asset_server.load("models/chess_kit/pieces.glb#Mesh2/Primitive0"),
+            knight_1: asset_server.load("models/chess_kit/pieces.glb#Mesh3/Primitive0"),
+            knight_2: asset_server.load("models/chess_kit/pieces.glb#Mesh4/Primitive0"),
+            rook: asset_server.load("models/chess_kit/pieces.glb#Mesh5/Primitive0"),
+            bishop: asset_server.load("models/chess_kit/pieces.glb#Mesh6/Primitive0"),
+            queen: asset_server.load("models/chess_kit/pieces.glb#Mesh7/Primitive0"),
+            white_material: materials.add(Color::rgb(0.8, 0.8, 0.8).into()),
+            black_material: materials.add(Color::rgb(0.2, 0.2, 0.2).into()),
         }
+    }
+}
 
-        false
+impl PieceMeshes {
+    fn material(&self, piece_color: PieceColor) -> Handle<StandardMaterial> {
+        match piece_color {
+            PieceColor::White => self.white_material.clone(),
+            PieceColor::Black => self.black_material.clone()
+        }
     }
 }
 
-pub struct PiecesPlugin;
+fn create_pieces(commands: &mut Commands, meshes: Res<PieceMeshes>, position: Res<Position>) {
+    for (square, piece) in position.pieces() {
+        spawn_piece(commands, &meshes, piece.color, piece.piece_type, (square.rank(), square.file()));
+    }
+}
 
-impl Plugin for PiecesPlugin {
-    fn build(&self, app: &mut AppBuilder) {
-        app.add_startup_system(create_pieces.system()).add_system(move_pieces.system());
+pub fn spawn_piece(commands: &mut Commands, meshes: &PieceMeshes, piece_color: PieceColor, piece_type: PieceType, position: (u8, u8)) {
+    let material = meshes.material(piece_color);
+
+    match piece_type {
+        PieceType::King => spawn_king(commands, material, piece_color, meshes.king.clone(), meshes.king_cross.clone(), position),
+        PieceType::Queen => spawn_queen(commands, material, piece_color, meshes.queen.clone(), position),
+        PieceType::Bishop => spawn_bishop(commands, material, piece_color, meshes.bishop.clone(), position),
+        PieceType::Knight => spawn_knight(commands, material, piece_color, meshes.knight_1.clone(), meshes.knight_2.clone(), position),
+        PieceType::Rook => spawn_rook(commands, material, piece_color, meshes.rook.clone(), position),
+        PieceType::Pawn => spawn_pawn(commands, material, piece_color, meshes.pawn.clone(), position),
     }
 }
 
-fn create_pieces(commands: &mut Commands, asset_server: ResMut<AssetServer>, mut materials: ResMut<Assets<StandardMaterial>>) {
-    let king_handle: Handle<Mesh> = asset_server.load("models/chess_kit/pieces.glb#Mesh0/Primitive0");
-    let king_cross_handle: Handle<Mesh> = asset_server.load("models/chess_kit/pieces.glb#Mesh1/Primitive0");
-    let pawn_handle: Handle<Mesh> = asset_server.load("models/chess_kit/pieces.glb#Mesh2/Primitive0");
-    let knight_1_handle: Handle<Mesh> = asset_server.load("models/chess_kit/pieces.glb#Mesh3/Primitive0");
-    let knight_2_handle: Handle<Mesh> = asset_server.load("models/chess_kit/pieces.glb#Mesh4/Primitive0");
-    let rook_handle: Handle<Mesh> = asset_server.load("models/chess_kit/pieces.glb#Mesh5/Primitive0");
-    let bishop_handle: Handle<Mesh> = asset_server.load("models/chess_kit/pieces.glb#Mesh6/Primitive0");
-    let queen_handle: Handle<Mesh> = asset_server.load("models/chess_kit/pieces.glb#Mesh7/Primitive0");
+// moves, spawns and despawns piece entities until they match the displayed position
+fn sync_pieces(commands: &mut Commands, meshes: Res<PieceMeshes>, displayed_position: ChangedRes<DisplayedPosition>, mut pieces_query: Query<(Entity, &mut Piece, &Children)>) {
+    let position = &displayed_position.0;
 
-    let white_material = materials.add(Color::rgb(0.8, 0.8, 0.8).into());
-    let black_material = materials.add(Color::rgb(0.2, 0.2, 0.2).into());
+    // squares that already have the right entity on them
+    let mut synced = [false; 64];
+    // entities that don't stand on a square with the same kind of piece anymore
+    let mut unsynced = Vec::new();
 
-    spawn_rook(commands, white_material.clone(), PieceColor::White, rook_handle.clone(), (0, 0));
-    spawn_knight(commands, white_material.clone(), PieceColor::White, knight_1_handle.clone(), knight_2_handle.clone(), (0, 1));
-    spawn_bishop(commands, white_material.clone(), PieceColor::White, bishop_handle.clone(), (0, 2));
-    spawn_queen(commands, white_material.clone(), PieceColor::White, queen_handle.clone(), (0, 3));
-    spawn_king(commands, white_material.clone(), PieceColor::White, king_handle.clone(), king_cross_handle.clone(), (0, 4));
-    spawn_bishop(commands, white_material.clone(), PieceColor::White, bishop_handle.clone(), (0, 5));
-    spawn_knight(commands, white_material.clone(), PieceColor::White, knight_1_handle.clone(), knight_2_handle.clone(), (0, 6));
-    spawn_rook(commands, white_material.clone(), PieceColor::White, rook_handle.clone(), (0, 7));
-    
-    for i in 0..8 {
-        spawn_pawn(commands, white_material.clone(), PieceColor::White, pawn_handle.clone(), (1, i));
+    for (entity, piece, _) in pieces_query.iter_mut() {
+        let square = chess_core::Square::new(piece.x, piece.y);
+        let position_piece = position.piece_at(square);
+
+        if !synced[square.index()] && position_piece == Some(chess_core::Piece::new(piece.color, piece.piece_type)) {
+            synced[square.index()] = true;
+        } else {
+            unsynced.push((entity, *piece));
+        }
     }
 
-    spawn_rook(commands, black_material.clone(), PieceColor::Black, rook_handle.clone(), (7, 0));
-    spawn_knight(commands, black_material.clone(), PieceColor::Black, knight_1_handle.clone(), knight_2_handle.clone(), (7, 1));
-    spawn_bishop(commands, black_material.clone(), PieceColor::Black, bishop_handle.clone(), (7, 2));
-    spawn_queen(commands, black_material.clone(), PieceColor::Black, queen_handle.clone(), (7, 3));
-    spawn_king(commands, black_material.clone(), PieceColor::Black, king_handle.clone(), king_cross_handle.clone(), (7, 4));
-    spawn_bishop(commands, black_material.clone(), PieceColor::Black, bishop_handle.clone(), (7, 5));
-    spawn_knight(commands, black_material.clone(), PieceColor::Black, knight_1_handle.clone(), knight_2_handle.clone(), (7, 6));
-    spawn_rook(commands, black_material.clone(), PieceColor::Black, rook_handle.clone(), (7, 7));
-    
-    for i in 0..8 {
-        spawn_pawn(commands, black_material.clone(), PieceColor::Black, pawn_handle.clone(), (6, i));
+    for (square, position_piece) in position.pieces().filter(|(square, _)| !synced[square.index()]) {
+        // move the closest entity of the same kind to the square, move_pieces animates it
+        let closest = unsynced.iter().enumerate()
+            .filter(|(_, (_, piece))| piece.color == position_piece.color && piece.piece_type == position_piece.piece_type)
+            .min_by_key(|(_, (_, piece))| (piece.x as i8 - square.rank() as i8).abs() + (piece.y as i8 - square.file() as i8).abs())
+            .map(|(index, _)| index);
+
+        if let Some(index) = closest {
+            let (entity, _) = unsynced.remove(index);
+            if let Ok((_entity, mut piece, _children)) = pieces_query.get_mut(entity) {
+                piece.x = square.rank();
+                piece.y = square.file();
+            }
+        } else {
+            spawn_piece(commands, &meshes, position_piece.color, position_piece.piece_type, (square.rank(), square.file()));
+        }
     }
 
+    // whatever is left was taken or promoted
+    for (entity, _) in unsynced {
+        commands.despawn(entity);
+        if let Ok((_entity, _piece, children)) = pieces_query.get_mut(entity) {
+            for child in children.iter() {
+                commands.despawn(*child);
+            }
+        }
+    }
 }
 
 fn spawn_king(commands: &mut Commands, material: Handle<StandardMaterial>, piece_color: PieceColor, mesh: Handle<Mesh>, mesh_cross: Handle<Mesh>, position: (u8, u8)) {
@@ -344,8 +316,13 @@ fn spawn_pawn(commands: &mut Commands, material: Handle<StandardMaterial>, piece
     });
 }
 
-fn move_pieces(time: Res<Time>, mut query: Query<(&mut Transform, &Piece)>) {
-    for (mut transform, piece) in query.iter_mut() {
+fn move_pieces(time: Res<Time>, dragged_piece: Res<DraggedPiece>, mut query: Query<(Entity, &mut Transform, &Piece)>) {
+    for (entity, mut transform, piece) in query.iter_mut() {
+        // the dragged piece follows the cursor instead
+        if dragged_piece.entity == Some(entity) {
+            continue;
+        }
+
         // get the direction to move in
         let direction = Vec3::new(piece.x as f32, 0., piece.y as f32) - transform.translation;
     
@@ -355,60 +332,3 @@ fn move_pieces(time: Res<Time>, mut query: Query<(&mut Transform, &Piece)>) {
         }
     }
 }
-
-// returns None if square is empty else it returns Some with the color
-fn color_of_square(position: (u8, u8), pieces: &Vec<Piece>) -> Option<PieceColor> {
-    for piece in pieces {
-        if piece.x == position.0 && piece.y == position.1 {
-            return Some(piece.color);
-        }
-    }
-    None
-}
-
-fn is_path_empty(begin: (u8, u8), end: (u8, u8), pieces: &Vec<Piece>) -> bool {
-    // same column
-    if begin.0 == end.0 {
-        for piece in pieces {
-            if piece.x == begin.0 && ((piece.y > begin.1 && piece.y < end.1) || (piece.y > end.1 && piece.y < begin.1)) {
-                return false;
-            }
-        }
-    }
-    // same row
-    if begin.1 == end.1 {
-        for piece in pieces {
-            if piece.y == begin.1 && ((piece.x > begin.0 && piece.x < end.0) || (piece.x > end.0 && piece.x < begin.0)) {
-                return false;
-            }
-        }
-    }
-
-    // diagonals
-    let x_diff = (begin.0 as i8 - end.0 as i8).abs();
-    let y_diff = (begin.1 as i8 - end.1 as i8).abs();
-    
-    if x_diff == y_diff {
-        for i in 1..x_diff {
-            let position = if begin.0 < end.0 && begin.1 < end.1 {
-                // left bottom - right top
-                (begin.0 + i as u8, begin.1 + i as u8)
-            } else if begin.0 < end.0 && begin.1 > end.1 {
-                // left top - right bottom
-                (begin.0 + i as u8, begin.1 - i as u8)
-            } else if begin.0 > end.0 && begin.1 < end.1 {
-                // right bottom - left top
-                (begin.0 - i as u8, begin.1 + i as u8)
-            } else {
-                // right top - left bottom
-                (begin.0 - i as u8, begin.1 - i as u8)
-            };
-
-            if color_of_square(position, pieces).is_some() {
-                return false;
-            }
-        }
-    }
-
-    true
-}
\ No newline at end of file
@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+
+use chess_core::{PgnGame, PgnMove, Position};
+
+use crate::board::*;
+
+// games loaded from a pgn file, the board shows them instead of a game that is played
+#[derive(Default)]
+pub struct Replay {
+    pub games: Vec<PgnGame>,
+    // index of the game on the board
+    pub game: usize,
+    // number of moves of its main line that are played on the board
+    pub ply: usize,
+}
+
+impl Replay {
+    pub fn is_active(&self) -> bool {
+        !self.games.is_empty()
+    }
+
+    pub fn current_game(&self) -> Option<&PgnGame> {
+        self.games.get(self.game)
+    }
+
+    // the position after the moves that are played on the board
+    pub fn position(&self) -> Option<Position> {
+        let game = self.current_game()?;
+        let mut position = game.start.clone();
+        for pgn_move in game.moves.iter().take(self.ply) {
+            position.make_move(pgn_move.mv);
+        }
+        Some(position)
+    }
+
+    // the last move played on the board with the position before it
+    pub fn last_move(&self) -> Option<(Position, &PgnMove)> {
+        let game = self.current_game()?;
+        let pgn_move = game.moves.get(self.ply.checked_sub(1)?)?;
+        let mut position = game.start.clone();
+        for previous in game.moves.iter().take(self.ply - 1) {
+            position.make_move(previous.mv);
+        }
+        Some((position, pgn_move))
+    }
+}
+
+// sent by the replay buttons and keys
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReplayControl {
+    Start,
+    Back,
+    Forward,
+    End,
+    PreviousGame,
+    NextGame,
+    Close,
+}
+
+pub struct ReplayPlugin {
+    pub games: Vec<PgnGame>,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // the first game replaces the start position, so the pieces are spawned for it
+        if let Some(game) = self.games.first() {
+            app.add_resource(game.start.clone());
+        }
+
+        app.add_resource(Replay { games: self.games.clone(), game: 0, ply: 0 }).add_event::<ReplayControl>()
+        .add_system(replay_keys.system()).add_system(replay_controls.system());
+    }
+}
+
+// arrows step through the moves, home and end jump to the start and end, page up and down change the game
+fn replay_keys(keyboard_input: Res<Input<KeyCode>>, replay: Res<Replay>, mut control_events: ResMut<Events<ReplayControl>>) {
+    if !replay.is_active() {
+        return;
+    }
+
+    let keys = [
+        (KeyCode::Home, ReplayControl::Start),
+        (KeyCode::Left, ReplayControl::Back),
+        (KeyCode::Right, ReplayControl::Forward),
+        (KeyCode::End, ReplayControl::End),
+        (KeyCode::PageUp, ReplayControl::PreviousGame),
+        (KeyCode::PageDown, ReplayControl::NextGame),
+    ];
+    for (key, control) in keys.iter() {
+        if keyboard_input.just_pressed(*key) {
+            control_events.send(*control);
+        }
+    }
+}
+
+// every step changes the position, sync_pieces moves the pieces to their new squares
+fn replay_controls(mut control_reader: Local<EventReader<ReplayControl>>, control_events: Res<Events<ReplayControl>>, mut replay: ResMut<Replay>, mut position: ResMut<Position>, mut new_game_events: ResMut<Events<NewGame>>) {
+    for control in control_reader.iter(&control_events) {
+        let moves = match replay.current_game() {
+            Some(game) => game.moves.len(),
+            None => return
+        };
+
+        match control {
+            ReplayControl::Start => replay.ply = 0,
+            ReplayControl::Back => replay.ply = replay.ply.saturating_sub(1),
+            ReplayControl::Forward => replay.ply = (replay.ply + 1).min(moves),
+            ReplayControl::End => replay.ply = moves,
+            ReplayControl::PreviousGame => {
+                replay.game = replay.game.saturating_sub(1);
+                replay.ply = 0;
+            }
+            ReplayControl::NextGame => {
+                replay.game = (replay.game + 1).min(replay.games.len() - 1);
+                replay.ply = 0;
+            }
+            ReplayControl::Close => {
+                // back to playing from the start position
+                *replay = Replay::default();
+                new_game_events.send(NewGame);
+                return;
+            }
+        }
+
+        if let Some(replay_position) = replay.position() {
+            *position = replay_position;
+        }
+    }
+}
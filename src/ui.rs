@@ -1,15 +1,160 @@
 use bevy::prelude::*;
+use bevy::{app::AppExit, input::mouse::MouseWheel};
 
-use crate::{board::*, pieces::*};
+use std::time::Duration;
+
+use chess_core::{GameEnd, Position, TimeControl, SKILLS};
+
+use crate::{ai::*, board::*, clock::*, pieces::*, replay::*};
 
 // component for text
 struct NextMoveText;
 
+// component for the text showing the check next to the next move
+struct CheckText;
+
+// component for the text with the line of the analyzing engine
+struct AnalysisText;
+
+// component for the text with the time left on the clock of a color
+struct ClockText(PieceColor);
+
+// component for the root node of the promotion chooser
+struct PromotionMenu;
+
+// component for the buttons of the promotion chooser
+struct PromotionButton(PieceType);
+
+// component for the root node of the menu to claim a draw
+struct ClaimDrawMenu;
+
+// component for the button to claim a draw
+struct ClaimDrawButton;
+
+// component for the root node of the game over menu
+struct GameOverMenu;
+
+// component for the buttons of the game over menu
+enum GameOverButton {
+    NewGame,
+    Quit,
+}
+
+// component for the root node of the replay controls
+struct ReplayPanel;
+
+// component for the text with the game and move of the replay
+struct ReplayText;
+
+// component for the buttons of the replay controls
+struct ReplayButton(ReplayControl);
+
+// component for the root node of the move history
+struct HistoryPanel;
+
+// component for the moves in the move history, with the number of moves played up to and including it
+struct HistoryButton(usize);
+
+// component for the buttons of the move history that control the game
+enum MoveControlButton {
+    Undo,
+    Redo,
+    NewGame,
+    Resign,
+    OfferDraw,
+    Abort,
+}
+
+// component for the root node of the new game dialog
+struct NewGameDialogNode;
+
+// component for the buttons of the new game dialog, the first three switch to the next choice
+enum NewGameDialogButton {
+    Player(PieceColor),
+    Skill(PieceColor),
+    Style(PieceColor),
+    TimeControl,
+    Start,
+    Cancel,
+}
+
+// the players are chosen in the dialog and take their seats when the new game starts
+#[derive(Default)]
+struct NewGameDialog {
+    open: bool,
+    players: Players,
+    time_control: Option<TimeControl>,
+}
+
+// component for the root node of the takeback request
+struct TakebackMenu;
+
+// component for the buttons to accept or decline a takeback
+struct TakebackButton(bool);
+
+// component for the root node of the draw offer
+struct DrawOfferMenu;
+
+// component for the buttons to accept or decline a draw offer
+struct DrawOfferButton(bool);
+
+// component for a button that keeps a different color while it isn't hovered
+struct Highlighted;
+
+// first row of the move history that is shown
+#[derive(Default)]
+struct HistoryScroll {
+    first_row: usize,
+}
+
+// time controls the new game dialog switches between, after playing without a clock
+const TIME_CONTROLS: [&str; 9] = ["1+0", "3+2", "5+0", "10+5", "15+10", "30+0", "5d3", "5b3", "40/90+30:30+30"];
+
+// a clock with less time left is shown in red
+const LOW_TIME: Duration = Duration::from_secs(20);
+
+// the move history shows this many rows and scrolls with the mouse wheel
+const HISTORY_ROWS: usize = 20;
+const HISTORY_ROW_HEIGHT: f32 = 28.;
+const HISTORY_WIDTH: f32 = 300.;
+const HISTORY_BUTTON_WIDTH: f32 = 110.;
+const HISTORY_TITLE_HEIGHT: f32 = 40.;
+// the title, the two rows of buttons and the rows of moves
+const HISTORY_HEIGHT: f32 = HISTORY_TITLE_HEIGHT + (HISTORY_ROWS + 2) as f32 * HISTORY_ROW_HEIGHT;
+
+// colors of the buttons in their different states
+struct ButtonMaterials {
+    normal: Handle<ColorMaterial>,
+    hovered: Handle<ColorMaterial>,
+    pressed: Handle<ColorMaterial>,
+    highlighted: Handle<ColorMaterial>,
+}
+
+impl FromResources for ButtonMaterials {
+    fn from_resources(resources: &Resources) -> Self {
+        let mut materials = resources.get_mut::<Assets<ColorMaterial>>().unwrap();
+        ButtonMaterials {
+            normal: materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+            hovered: materials.add(Color::rgb(0.25, 0.25, 0.25).into()),
+            pressed: materials.add(Color::rgb(0.35, 0.35, 0.35).into()),
+            highlighted: materials.add(Color::rgb(0.5, 0.2, 0.2).into()),
+        }
+    }
+}
+
 pub struct UIPlugin;
 
 impl Plugin for UIPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_startup_system(init_next_move_text.system()).add_system(next_move_text_update.system());
+        app.init_resource::<ButtonMaterials>().init_resource::<HistoryScroll>().init_resource::<NewGameDialog>().add_startup_system(init_next_move_text.system()).add_system(next_move_text_update.system()).add_system(check_text_update.system())
+        .add_system(analysis_text_update.system()).add_startup_system(init_clock_text.system()).add_system(clock_text_update.system()).add_system(button_colors.system()).add_system(promotion_menu.system()).add_system(promotion_buttons.system())
+        .add_system(claim_draw_menu.system()).add_system(claim_draw_buttons.system())
+        .add_system(game_over_menu.system()).add_system(game_over_buttons.system())
+        .add_system(replay_panel.system()).add_system(replay_text_update.system()).add_system(replay_buttons.system())
+        .add_system(follow_current_move.system()).add_system(scroll_history.system()).add_system(history_panel.system())
+        .add_system(history_buttons.system()).add_system(history_keys.system()).add_system(move_control_buttons.system())
+        .add_system(takeback_menu.system()).add_system(takeback_buttons.system()).add_system(draw_offer_menu.system()).add_system(draw_offer_buttons.system())
+        .add_system(new_game_keys.system()).add_system(new_game_dialog.system()).add_system(new_game_dialog_buttons.system());
     }
 }
 
@@ -35,7 +180,7 @@ fn init_next_move_text(commands: &mut Commands, asset_server: ResMut<AssetServer
         parent.spawn(TextBundle {
             text: Text {
                 value: "next move: white".to_string(), 
-                font, 
+                font: font.clone(), 
                 style: TextStyle {
                     font_size: 28., 
                     color: Color::rgb(0.9, 0.9, 0.9),
@@ -44,14 +189,981 @@ fn init_next_move_text(commands: &mut Commands, asset_server: ResMut<AssetServer
             }, 
             ..Default::default()
         }).with(NextMoveText);
-    });
+        parent.spawn(TextBundle {
+            text: Text {
+                value: "".to_string(),
+                font: font.clone(),
+                style: TextStyle {
+                    font_size: 28.,
+                    color: Color::rgb(0.9, 0.1, 0.1),
+                    ..Default::default()
+                }
+            },
+            style: Style {
+                margin: Rect {
+                    left: Val::Px(20.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        }).with(CheckText);
+    })
+    // below the next move
+    .spawn(TextBundle {
+        text: Text {
+            value: "".to_string(),
+            font,
+            style: TextStyle {
+                font_size: 20.,
+                color: Color::rgb(0.7, 0.7, 0.7),
+                ..Default::default()
+            }
+        },
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                left: Val::Px(10.),
+                top: Val::Px(45.),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        ..Default::default()
+    }).with(AnalysisText);
 } 
 
+// the clocks sit left of the move history, white above black
+fn init_clock_text(commands: &mut Commands, asset_server: ResMut<AssetServer>, mut color_materials: ResMut<Assets<ColorMaterial>>) {
+    let font = asset_server.load("fonts/Menlo-Regular-01.ttf");
+
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                right: Val::Px(20. + HISTORY_WIDTH),
+                top: Val::Px(10.),
+                ..Default::default()
+            },
+            // the rows go from the top to the bottom
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::FlexEnd,
+            ..Default::default()
+        },
+        material: color_materials.add(Color::NONE.into()),
+        ..Default::default()
+    }).with_children(|parent| {
+        for color in [PieceColor::White, PieceColor::Black].iter() {
+            parent.spawn(TextBundle {
+                text: Text {
+                    value: "".to_string(),
+                    font: font.clone(),
+                    style: TextStyle {
+                        font_size: 28.,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..Default::default()
+                    }
+                },
+                ..Default::default()
+            }).with(ClockText(*color));
+        }
+    });
+}
+
+// the running clock is brighter than the waiting one, both turn red when little time is left
+fn clock_text_update(game_clock: ChangedRes<GameClock>, game_result: Res<GameResult>, mut query: Query<(&mut Text, &ClockText)>) {
+    for (mut text, clock_text) in query.iter_mut() {
+        let clock = match &game_clock.clock {
+            Some(clock) => clock,
+            None => {
+                text.value = "".to_string();
+                continue;
+            }
+        };
+
+        let remaining = clock.remaining(clock_text.0);
+        let running = clock.turn() == clock_text.0 && game_clock.started() && game_result.0.is_none();
+        text.value = format!("{} {}", color_name(clock_text.0), clock_time(remaining));
+        text.style.color = match (remaining < LOW_TIME, running) {
+            (true, true) => Color::rgb(1., 0.2, 0.2),
+            (true, false) => Color::rgb(0.7, 0.2, 0.2),
+            (false, true) => Color::rgb(0.95, 0.95, 0.95),
+            (false, false) => Color::rgb(0.55, 0.55, 0.55),
+        };
+    }
+}
+
+// "1:30:00", "4:59" and with tenths of seconds under ten seconds "0:09.4"
+fn clock_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    } else if seconds >= 10 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!("0:{:02}.{}", seconds, time.subsec_millis() / 100)
+    }
+}
+
 fn next_move_text_update(turn: ChangedRes<PlayerTurn>, mut query: Query<(&mut Text, &NextMoveText)>) {
     for (mut text, _tag) in query.iter_mut() {
-        text.value = format!("next move: {}", match turn.0 {
-            PieceColor::White => "white",
-            PieceColor::Black => "black"
+        text.value = format!("next move: {}", color_name(turn.0));
+    }
+}
+
+fn analysis_text_update(analysis: ChangedRes<Analysis>, mut query: Query<(&mut Text, &AnalysisText)>) {
+    for (mut text, _tag) in query.iter_mut() {
+        text.value = analysis.text.clone();
+    }
+}
+
+fn check_text_update(position: ChangedRes<Position>, mut query: Query<(&mut Text, &CheckText)>) {
+    for (mut text, _tag) in query.iter_mut() {
+        text.value = if position.is_check() { "check".to_string() } else { "".to_string() };
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn button_colors(button_materials: Res<ButtonMaterials>, mut query: Query<(&Interaction, &mut Handle<ColorMaterial>, Option<&Highlighted>), (Mutated<Interaction>, With<Button>)>) {
+    for (interaction, mut material, highlighted) in query.iter_mut() {
+        *material = match *interaction {
+            Interaction::Clicked => button_materials.pressed.clone(),
+            Interaction::Hovered => button_materials.hovered.clone(),
+            Interaction::None if highlighted.is_some() => button_materials.highlighted.clone(),
+            Interaction::None => button_materials.normal.clone(),
+        };
+    }
+}
+
+// spawns a button with a label, the component tells the button systems which button was clicked
+fn spawn_button<T: Component>(parent: &mut ChildBuilder, button_materials: &ButtonMaterials, font: Handle<Font>, label: &str, component: T) {
+    parent.spawn(ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Px(140.), Val::Px(40.)),
+            margin: Rect::all(Val::Px(5.)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        material: button_materials.normal.clone(),
+        ..Default::default()
+    }).with(component)
+    .with_children(|parent| {
+        parent.spawn(TextBundle {
+            text: Text {
+                value: label.to_string(),
+                font,
+                style: TextStyle {
+                    font_size: 24.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                }
+            },
+            ..Default::default()
         });
+    });
+}
+
+// spawns a text used as the title of a menu
+fn spawn_menu_title(parent: &mut ChildBuilder, font: Handle<Font>, title: &str) {
+    parent.spawn(TextBundle {
+        text: Text {
+            value: title.to_string(),
+            font,
+            style: TextStyle {
+                font_size: 28.,
+                color: Color::rgb(0.9, 0.9, 0.9),
+                ..Default::default()
+            }
+        },
+        style: Style {
+            margin: Rect::all(Val::Px(5.)),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+}
+
+// root node of a menu at the bottom left of the window
+fn menu_node(color_materials: &mut Assets<ColorMaterial>) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                left: Val::Px(10.),
+                bottom: Val::Px(10.),
+                ..Default::default()
+            },
+            align_items: AlignItems::Center,
+            padding: Rect::all(Val::Px(5.)),
+            ..Default::default()
+        },
+        material: color_materials.add(Color::rgba(0., 0., 0., 0.6).into()),
+        ..Default::default()
+    }
+}
+
+// opens the chooser while a pawn waits for its promotion and closes it afterwards
+fn promotion_menu(commands: &mut Commands, promotion: Res<Promotion>, asset_server: Res<AssetServer>, button_materials: Res<ButtonMaterials>, mut color_materials: ResMut<Assets<ColorMaterial>>, menu_query: Query<Entity, With<PromotionMenu>>) {
+    let menu_entity = menu_query.iter().next();
+
+    if promotion.pending_move.is_none() {
+        if let Some(entity) = menu_entity {
+            commands.despawn_recursive(entity);
+        }
+        return;
     }
-}
\ No newline at end of file
+
+    if menu_entity.is_some() {
+        return;
+    }
+
+    let font = asset_server.load("fonts/Menlo-Regular-01.ttf");
+
+    commands.spawn(menu_node(&mut color_materials)).with(PromotionMenu)
+    .with_children(|parent| {
+        spawn_menu_title(parent, font.clone(), "promote to:");
+        spawn_button(parent, &button_materials, font.clone(), "queen", PromotionButton(PieceType::Queen));
+        spawn_button(parent, &button_materials, font.clone(), "rook", PromotionButton(PieceType::Rook));
+        spawn_button(parent, &button_materials, font.clone(), "bishop", PromotionButton(PieceType::Bishop));
+        spawn_button(parent, &button_materials, font, "knight", PromotionButton(PieceType::Knight));
+    });
+}
+
+fn promotion_buttons(mut choice_events: ResMut<Events<PromotionChoice>>, query: Query<(&Interaction, &PromotionButton), Mutated<Interaction>>) {
+    for (interaction, button) in query.iter() {
+        if *interaction == Interaction::Clicked {
+            choice_events.send(PromotionChoice(button.0));
+        }
+    }
+}
+
+// offers the player to move to claim a draw as long as the fifty-move rule or threefold repetition allows it
+#[allow(clippy::too_many_arguments)]
+fn claim_draw_menu(commands: &mut Commands, position: Res<Position>, game_result: Res<GameResult>, promotion: Res<Promotion>, replay: Res<Replay>, takeback: Res<Takeback>, asset_server: Res<AssetServer>, button_materials: Res<ButtonMaterials>, mut color_materials: ResMut<Assets<ColorMaterial>>, menu_query: Query<Entity, With<ClaimDrawMenu>>) {
+    let menu_entity = menu_query.iter().next();
+
+    let claimable_draw = match position.claimable_draw() {
+        Some(claimable_draw) if game_result.0.is_none() && promotion.pending_move.is_none() && !replay.is_active() && takeback.requested_by.is_none() => claimable_draw,
+        _ => {
+            if let Some(entity) = menu_entity {
+                commands.despawn_recursive(entity);
+            }
+            return;
+        }
+    };
+
+    if menu_entity.is_some() {
+        return;
+    }
+
+    let font = asset_server.load("fonts/Menlo-Regular-01.ttf");
+    let title = match claimable_draw {
+        GameEnd::ThreefoldRepetition => "threefold repetition",
+        _ => "fifty moves without capture or pawn move"
+    };
+
+    commands.spawn(menu_node(&mut color_materials)).with(ClaimDrawMenu)
+    .with_children(|parent| {
+        spawn_menu_title(parent, font.clone(), title);
+        spawn_button(parent, &button_materials, font, "claim draw", ClaimDrawButton);
+    });
+}
+
+fn claim_draw_buttons(mut claim_events: ResMut<Events<ClaimDraw>>, query: Query<&Interaction, (Mutated<Interaction>, With<ClaimDrawButton>)>) {
+    for interaction in query.iter() {
+        if *interaction == Interaction::Clicked {
+            claim_events.send(ClaimDraw);
+        }
+    }
+}
+
+fn color_name(color: PieceColor) -> &'static str {
+    match color {
+        PieceColor::White => "white",
+        PieceColor::Black => "black"
+    }
+}
+
+fn game_end_text(game_end: GameEnd) -> String {
+    match game_end {
+        GameEnd::Checkmate(winner) => format!("checkmate, {} wins", color_name(winner)),
+        GameEnd::Stalemate => "stalemate, draw".to_string(),
+        GameEnd::InsufficientMaterial => "insufficient material, draw".to_string(),
+        GameEnd::FiftyMoveRule => "fifty-move rule, draw".to_string(),
+        GameEnd::SeventyFiveMoveRule => "seventy-five-move rule, draw".to_string(),
+        GameEnd::ThreefoldRepetition => "threefold repetition, draw".to_string(),
+        GameEnd::FivefoldRepetition => "fivefold repetition, draw".to_string(),
+        GameEnd::Timeout(winner) => format!("{} ran out of time, {} wins", color_name(winner.opposite()), color_name(winner)),
+        GameEnd::TimeoutVsInsufficientMaterial => "time ran out against insufficient material, draw".to_string(),
+        GameEnd::Resignation(winner) => format!("{} resigns, {} wins", color_name(winner.opposite()), color_name(winner)),
+        GameEnd::DrawAgreement => "draw by agreement".to_string(),
+        GameEnd::Aborted => "game aborted".to_string(),
+    }
+}
+
+// opens the game over menu when the game ended and closes it for a new game
+fn game_over_menu(commands: &mut Commands, game_result: Res<GameResult>, asset_server: Res<AssetServer>, button_materials: Res<ButtonMaterials>, mut color_materials: ResMut<Assets<ColorMaterial>>, menu_query: Query<Entity, With<GameOverMenu>>) {
+    let menu_entity = menu_query.iter().next();
+
+    let game_end = match game_result.0 {
+        Some(game_end) => game_end,
+        None => {
+            if let Some(entity) = menu_entity {
+                commands.despawn_recursive(entity);
+            }
+            return;
+        }
+    };
+
+    if menu_entity.is_some() {
+        return;
+    }
+
+    let font = asset_server.load("fonts/Menlo-Regular-01.ttf");
+
+    commands.spawn(menu_node(&mut color_materials)).with(GameOverMenu)
+    .with_children(|parent| {
+        spawn_menu_title(parent, font.clone(), &game_end_text(game_end));
+        spawn_button(parent, &button_materials, font.clone(), "new game", GameOverButton::NewGame);
+        spawn_button(parent, &button_materials, font, "quit", GameOverButton::Quit);
+    });
+}
+
+fn game_over_buttons(players: Res<Players>, game_clock: Res<GameClock>, mut dialog: ResMut<NewGameDialog>, mut app_exit_events: ResMut<Events<AppExit>>, query: Query<(&Interaction, &GameOverButton), Mutated<Interaction>>) {
+    for (interaction, button) in query.iter() {
+        if *interaction == Interaction::Clicked {
+            match button {
+                GameOverButton::NewGame => open_new_game_dialog(&mut dialog, &players, &game_clock),
+                GameOverButton::Quit => app_exit_events.send(AppExit),
+            }
+        }
+    }
+}
+
+// shows the replay controls while games from a pgn file are on the board
+fn replay_panel(commands: &mut Commands, replay: Res<Replay>, asset_server: Res<AssetServer>, button_materials: Res<ButtonMaterials>, mut color_materials: ResMut<Assets<ColorMaterial>>, panel_query: Query<Entity, With<ReplayPanel>>) {
+    let panel_entity = panel_query.iter().next();
+
+    if !replay.is_active() {
+        if let Some(entity) = panel_entity {
+            commands.despawn_recursive(entity);
+        }
+        return;
+    }
+
+    if panel_entity.is_some() {
+        return;
+    }
+
+    let font = asset_server.load("fonts/Menlo-Regular-01.ttf");
+    let row_material = color_materials.add(Color::NONE.into());
+
+    // the text above the row of buttons
+    let mut panel = menu_node(&mut color_materials);
+    panel.style.flex_direction = FlexDirection::ColumnReverse;
+    panel.style.align_items = AlignItems::FlexStart;
+
+    commands.spawn(panel).with(ReplayPanel)
+    .with_children(|parent| {
+        parent.spawn(TextBundle {
+            text: Text {
+                value: replay_text(&replay),
+                font: font.clone(),
+                style: TextStyle {
+                    font_size: 24.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                }
+            },
+            style: Style {
+                margin: Rect::all(Val::Px(5.)),
+                ..Default::default()
+            },
+            ..Default::default()
+        }).with(ReplayText);
+
+        parent.spawn(NodeBundle {
+            material: row_material,
+            ..Default::default()
+        }).with_children(|parent| {
+            spawn_button(parent, &button_materials, font.clone(), "start", ReplayButton(ReplayControl::Start));
+            spawn_button(parent, &button_materials, font.clone(), "back", ReplayButton(ReplayControl::Back));
+            spawn_button(parent, &button_materials, font.clone(), "forward", ReplayButton(ReplayControl::Forward));
+            spawn_button(parent, &button_materials, font.clone(), "end", ReplayButton(ReplayControl::End));
+            spawn_button(parent, &button_materials, font.clone(), "prev game", ReplayButton(ReplayControl::PreviousGame));
+            spawn_button(parent, &button_materials, font.clone(), "next game", ReplayButton(ReplayControl::NextGame));
+            spawn_button(parent, &button_materials, font, "close", ReplayButton(ReplayControl::Close));
+        });
+    });
+}
+
+fn replay_text_update(replay: ChangedRes<Replay>, mut query: Query<&mut Text, With<ReplayText>>) {
+    for mut text in query.iter_mut() {
+        text.value = replay_text(&replay);
+    }
+}
+
+fn replay_buttons(mut control_events: ResMut<Events<ReplayControl>>, query: Query<(&Interaction, &ReplayButton), Mutated<Interaction>>) {
+    for (interaction, button) in query.iter() {
+        if *interaction == Interaction::Clicked {
+            control_events.send(button.0);
+        }
+    }
+}
+
+// the players of the game and the last move with its annotations, like "game 1/2: alice - bob, 2. Nf3! {good}"
+fn replay_text(replay: &Replay) -> String {
+    let game = match replay.current_game() {
+        Some(game) => game,
+        None => return "".to_string()
+    };
+
+    let mut text = format!("game {}/{}: {} - {}", replay.game + 1, replay.games.len(), game.tag("White").unwrap_or("?"), game.tag("Black").unwrap_or("?"));
+    match replay.last_move() {
+        Some((position, pgn_move)) => {
+            let dots = if position.side_to_move() == PieceColor::White { "." } else { "..." };
+            text.push_str(&format!(", {}{} {}", position.fullmove_number(), dots, position.san(pgn_move.mv)));
+            for nag in pgn_move.nags.iter() {
+                text.push_str(&nag_text(*nag));
+            }
+            if let Some(comment) = &pgn_move.comment {
+                text.push_str(&format!(" {{{}}}", comment));
+            }
+        }
+        None => text.push_str(", start")
+    }
+    text
+}
+
+// the symbols for the common annotation glyphs, the others keep their number
+fn nag_text(nag: u8) -> String {
+    match nag {
+        1 => "!".to_string(),
+        2 => "?".to_string(),
+        3 => "!!".to_string(),
+        4 => "??".to_string(),
+        5 => "!?".to_string(),
+        6 => "?!".to_string(),
+        _ => format!(" ${}", nag)
+    }
+}
+
+// the moves are numbered in rows of a white and a black move, a game from a fen can start with black
+struct HistoryLayout {
+    first_number: u32,
+    // the first row has no white move
+    black_first: bool,
+    moves: usize,
+}
+
+impl HistoryLayout {
+    fn new(start_position: &StartPosition, move_list: &MoveList) -> HistoryLayout {
+        HistoryLayout {
+            first_number: start_position.0.fullmove_number(),
+            black_first: start_position.0.side_to_move() == PieceColor::Black,
+            moves: move_list.moves.len(),
+        }
+    }
+
+    fn rows(&self) -> usize {
+        (self.moves + self.black_first as usize).div_ceil(2)
+    }
+
+    // row of the move with the given index
+    fn row(&self, index: usize) -> usize {
+        (index + self.black_first as usize) / 2
+    }
+
+    // index of the move in the row and column, None for an empty cell
+    fn index(&self, row: usize, column: usize) -> Option<usize> {
+        (row * 2 + column).checked_sub(self.black_first as usize).filter(|index| *index < self.moves)
+    }
+}
+
+// scrolls the move history so the move on the board stays visible
+fn follow_current_move(start_position: Res<StartPosition>, move_list: Res<MoveList>, history_view: Res<HistoryView>, mut scroll: ResMut<HistoryScroll>, mut followed: Local<(usize, Option<usize>)>) {
+    if *followed == (move_list.moves.len(), history_view.ply) {
+        return;
+    }
+    *followed = (move_list.moves.len(), history_view.ply);
+
+    let layout = HistoryLayout::new(&start_position, &move_list);
+    let ply = history_view.ply.unwrap_or(layout.moves);
+    let row = layout.row(ply.saturating_sub(1));
+    if row < scroll.first_row {
+        scroll.first_row = row;
+    } else if row >= scroll.first_row + HISTORY_ROWS {
+        scroll.first_row = row + 1 - HISTORY_ROWS;
+    }
+}
+
+// the mouse wheel scrolls the move history while the cursor is over it
+fn scroll_history(mut wheel_reader: Local<EventReader<MouseWheel>>, wheel_events: Res<Events<MouseWheel>>, windows: Res<Windows>, start_position: Res<StartPosition>, move_list: Res<MoveList>, mut scroll: ResMut<HistoryScroll>) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return
+    };
+    // the cursor position starts at the bottom left, the panel is at the top right
+    let over_panel = window.cursor_position().is_some_and(|cursor| cursor.x >= window.width() - 10. - HISTORY_WIDTH
+        && cursor.y >= window.height() - 10. - HISTORY_HEIGHT);
+
+    let max_first_row = HistoryLayout::new(&start_position, &move_list).rows().saturating_sub(HISTORY_ROWS);
+    for wheel in wheel_reader.iter(&wheel_events) {
+        if !over_panel {
+            continue;
+        }
+        if wheel.y > 0. {
+            scroll.first_row = scroll.first_row.saturating_sub(1);
+        } else if wheel.y < 0. {
+            scroll.first_row = (scroll.first_row + 1).min(max_first_row);
+        }
+    }
+}
+
+// builds the move history again whenever a move is played, the view changes or it scrolls
+#[allow(clippy::too_many_arguments)]
+fn history_panel(commands: &mut Commands, start_position: Res<StartPosition>, move_list: Res<MoveList>, history_view: Res<HistoryView>, scroll: Res<HistoryScroll>, replay: Res<Replay>, asset_server: Res<AssetServer>, button_materials: Res<ButtonMaterials>, mut color_materials: ResMut<Assets<ColorMaterial>>, panel_query: Query<Entity, With<HistoryPanel>>, mut shown: Local<Option<(usize, Option<usize>, usize)>>) {
+    let panel_entity = panel_query.iter().next();
+
+    // a replay has its own controls
+    let state = if replay.is_active() { None } else { Some((move_list.moves.len(), history_view.ply, scroll.first_row)) };
+    if state == *shown && (panel_entity.is_some() || state.is_none()) {
+        return;
+    }
+    *shown = state;
+
+    if let Some(entity) = panel_entity {
+        commands.despawn_recursive(entity);
+    }
+    if state.is_none() {
+        return;
+    }
+
+    let font = asset_server.load("fonts/Menlo-Regular-01.ttf");
+    let row_material = color_materials.add(Color::NONE.into());
+    let layout = HistoryLayout::new(&start_position, &move_list);
+    let current_ply = history_view.ply.unwrap_or(layout.moves);
+    let text_style = TextStyle {
+        font_size: 20.,
+        color: Color::rgb(0.9, 0.9, 0.9),
+        ..Default::default()
+    };
+
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                right: Val::Px(10.),
+                top: Val::Px(10.),
+                ..Default::default()
+            },
+            size: Size::new(Val::Px(HISTORY_WIDTH), Val::Px(HISTORY_HEIGHT)),
+            // the rows go from the top to the bottom
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::FlexStart,
+            ..Default::default()
+        },
+        material: color_materials.add(Color::rgba(0., 0., 0., 0.6).into()),
+        ..Default::default()
+    }).with(HistoryPanel)
+    .with_children(|parent| {
+        if history_view.ply.is_some() {
+            // the last move leads back to the live position
+            spawn_small_button(parent, &button_materials, font.clone(), "back to game", HistoryButton(layout.moves), HISTORY_BUTTON_WIDTH, false);
+        } else {
+            spawn_menu_title(parent, font.clone(), "moves");
+        }
+
+        parent.spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Px(HISTORY_ROW_HEIGHT)),
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: row_material.clone(),
+            ..Default::default()
+        }).with_children(|parent| {
+            spawn_small_button(parent, &button_materials, font.clone(), "undo", MoveControlButton::Undo, 90., false);
+            spawn_small_button(parent, &button_materials, font.clone(), "redo", MoveControlButton::Redo, 90., false);
+            spawn_small_button(parent, &button_materials, font.clone(), "new", MoveControlButton::NewGame, 90., false);
+        });
+
+        parent.spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Px(HISTORY_ROW_HEIGHT)),
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: row_material.clone(),
+            ..Default::default()
+        }).with_children(|parent| {
+            spawn_small_button(parent, &button_materials, font.clone(), "resign", MoveControlButton::Resign, 90., false);
+            spawn_small_button(parent, &button_materials, font.clone(), "draw", MoveControlButton::OfferDraw, 90., false);
+            spawn_small_button(parent, &button_materials, font.clone(), "abort", MoveControlButton::Abort, 90., false);
+        });
+
+        for row in scroll.first_row..layout.rows().min(scroll.first_row + HISTORY_ROWS) {
+            parent.spawn(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.), Val::Px(HISTORY_ROW_HEIGHT)),
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                material: row_material.clone(),
+                ..Default::default()
+            }).with_children(|parent| {
+                parent.spawn(TextBundle {
+                    text: Text {
+                        value: format!("{}.", layout.first_number as usize + row),
+                        font: font.clone(),
+                        style: text_style.clone(),
+                    },
+                    style: Style {
+                        size: Size::new(Val::Px(50.), Val::Auto),
+                        margin: Rect::all(Val::Px(5.)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                });
+                for column in 0..2 {
+                    match layout.index(row, column) {
+                        Some(index) => spawn_small_button(parent, &button_materials, font.clone(), &move_list.moves[index].san, HistoryButton(index + 1), HISTORY_BUTTON_WIDTH, index + 1 == current_ply),
+                        None => {
+                            parent.spawn(NodeBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(HISTORY_BUTTON_WIDTH), Val::Px(HISTORY_ROW_HEIGHT - 4.)),
+                                    margin: Rect::all(Val::Px(2.)),
+                                    ..Default::default()
+                                },
+                                material: row_material.clone(),
+                                ..Default::default()
+                            });
+                        }
+                    }
+                }
+            });
+        }
+    });
+}
+
+// a smaller button than the menu buttons for the move history and the new game dialog
+#[allow(clippy::too_many_arguments)]
+fn spawn_small_button<T: Component>(parent: &mut ChildBuilder, button_materials: &ButtonMaterials, font: Handle<Font>, label: &str, component: T, width: f32, highlighted: bool) {
+    parent.spawn(ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Px(width), Val::Px(HISTORY_ROW_HEIGHT - 4.)),
+            margin: Rect::all(Val::Px(2.)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        material: if highlighted { button_materials.highlighted.clone() } else { button_materials.normal.clone() },
+        ..Default::default()
+    }).with(component);
+    if highlighted {
+        parent.with(Highlighted);
+    }
+    parent.with_children(|parent| {
+        parent.spawn(TextBundle {
+            text: Text {
+                value: label.to_string(),
+                font,
+                style: TextStyle {
+                    font_size: 20.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                }
+            },
+            ..Default::default()
+        });
+    });
+}
+
+// clicking a move shows the position after it, clicking the last move goes back to the live game
+fn history_buttons(move_list: Res<MoveList>, promotion: Res<Promotion>, mut history_view: ResMut<HistoryView>, query: Query<(&Interaction, &HistoryButton), Mutated<Interaction>>) {
+    for (interaction, button) in query.iter() {
+        // the pawn waiting for its promotion isn't in the move list yet
+        if *interaction == Interaction::Clicked && promotion.pending_move.is_none() {
+            history_view.ply = if button.0 >= move_list.moves.len() { None } else { Some(button.0) };
+        }
+    }
+}
+
+// escape goes back to the live game
+fn history_keys(keyboard_input: Res<Input<KeyCode>>, mut history_view: ResMut<HistoryView>) {
+    if keyboard_input.just_pressed(KeyCode::Escape) && history_view.ply.is_some() {
+        history_view.ply = None;
+    }
+}
+
+// the game actions only send their events, the board decides whether they are allowed
+#[allow(clippy::too_many_arguments)]
+fn move_control_buttons(mut undo_events: ResMut<Events<UndoMove>>, mut redo_events: ResMut<Events<RedoMove>>, mut resign_events: ResMut<Events<Resign>>, mut offer_events: ResMut<Events<OfferDraw>>, mut abort_events: ResMut<Events<Abort>>, players: Res<Players>, game_clock: Res<GameClock>, mut dialog: ResMut<NewGameDialog>, query: Query<(&Interaction, &MoveControlButton), Mutated<Interaction>>) {
+    for (interaction, button) in query.iter() {
+        if *interaction == Interaction::Clicked {
+            match button {
+                MoveControlButton::Undo => undo_events.send(UndoMove),
+                MoveControlButton::Redo => redo_events.send(RedoMove),
+                MoveControlButton::NewGame => open_new_game_dialog(&mut dialog, &players, &game_clock),
+                MoveControlButton::Resign => resign_events.send(Resign),
+                MoveControlButton::OfferDraw => offer_events.send(OfferDraw),
+                MoveControlButton::Abort => abort_events.send(Abort),
+            }
+        }
+    }
+}
+
+// asks the opponent to accept or decline while a player wants to take back their move
+fn takeback_menu(commands: &mut Commands, takeback: Res<Takeback>, asset_server: Res<AssetServer>, button_materials: Res<ButtonMaterials>, mut color_materials: ResMut<Assets<ColorMaterial>>, menu_query: Query<Entity, With<TakebackMenu>>) {
+    let menu_entity = menu_query.iter().next();
+
+    let requested_by = match takeback.requested_by {
+        Some(requested_by) => requested_by,
+        None => {
+            if let Some(entity) = menu_entity {
+                commands.despawn_recursive(entity);
+            }
+            return;
+        }
+    };
+
+    if menu_entity.is_some() {
+        return;
+    }
+
+    let font = asset_server.load("fonts/Menlo-Regular-01.ttf");
+
+    commands.spawn(menu_node(&mut color_materials)).with(TakebackMenu)
+    .with_children(|parent| {
+        spawn_menu_title(parent, font.clone(), &format!("{} asks to take back a move", color_name(requested_by)));
+        spawn_button(parent, &button_materials, font.clone(), "accept", TakebackButton(true));
+        spawn_button(parent, &button_materials, font, "decline", TakebackButton(false));
+    });
+}
+
+fn takeback_buttons(mut answer_events: ResMut<Events<TakebackAnswer>>, query: Query<(&Interaction, &TakebackButton), Mutated<Interaction>>) {
+    for (interaction, button) in query.iter() {
+        if *interaction == Interaction::Clicked {
+            answer_events.send(TakebackAnswer(button.0));
+        }
+    }
+}
+
+// asks the opponent to accept or decline while a draw is offered
+fn draw_offer_menu(commands: &mut Commands, draw_offer: Res<DrawOffer>, game_result: Res<GameResult>, asset_server: Res<AssetServer>, button_materials: Res<ButtonMaterials>, mut color_materials: ResMut<Assets<ColorMaterial>>, menu_query: Query<Entity, With<DrawOfferMenu>>) {
+    let menu_entity = menu_query.iter().next();
+
+    let offered_by = match draw_offer.offered_by.filter(|_| game_result.0.is_none()) {
+        Some(offered_by) => offered_by,
+        None => {
+            if let Some(entity) = menu_entity {
+                commands.despawn_recursive(entity);
+            }
+            return;
+        }
+    };
+
+    if menu_entity.is_some() {
+        return;
+    }
+
+    let font = asset_server.load("fonts/Menlo-Regular-01.ttf");
+
+    commands.spawn(menu_node(&mut color_materials)).with(DrawOfferMenu)
+    .with_children(|parent| {
+        spawn_menu_title(parent, font.clone(), &format!("{} offers a draw", color_name(offered_by)));
+        spawn_button(parent, &button_materials, font.clone(), "accept", DrawOfferButton(true));
+        spawn_button(parent, &button_materials, font, "decline", DrawOfferButton(false));
+    });
+}
+
+fn draw_offer_buttons(mut answer_events: ResMut<Events<DrawAnswer>>, query: Query<(&Interaction, &DrawOfferButton), Mutated<Interaction>>) {
+    for (interaction, button) in query.iter() {
+        if *interaction == Interaction::Clicked {
+            answer_events.send(DrawAnswer(button.0));
+        }
+    }
+}
+
+// the dialog starts with the players and the time control of the current game
+fn open_new_game_dialog(dialog: &mut NewGameDialog, players: &Players, game_clock: &GameClock) {
+    dialog.open = true;
+    dialog.players = *players;
+    dialog.time_control = game_clock.control.clone();
+}
+
+// ctrl+n opens the new game dialog
+fn new_game_keys(keyboard_input: Res<Input<KeyCode>>, players: Res<Players>, game_clock: Res<GameClock>, mut dialog: ResMut<NewGameDialog>) {
+    let ctrl = keyboard_input.pressed(KeyCode::LControl) || keyboard_input.pressed(KeyCode::RControl);
+    if ctrl && keyboard_input.just_pressed(KeyCode::N) && !dialog.open {
+        open_new_game_dialog(&mut dialog, &players, &game_clock);
+    }
+}
+
+fn player_text(config: &PlayerConfig, engines: &Engines) -> String {
+    match config.player {
+        Player::Human => "human".to_string(),
+        Player::Computer => "computer".to_string(),
+        Player::Engine(index) => engines.name(index).to_string(),
+    }
+}
+
+// human, the built-in computer and then every engine
+fn next_player(player: Player, engines: &Engines) -> Player {
+    match player {
+        Player::Human => Player::Computer,
+        Player::Computer if !engines.0.is_empty() => Player::Engine(0),
+        Player::Engine(index) if index + 1 < engines.0.len() => Player::Engine(index + 1),
+        _ => Player::Human
+    }
+}
+
+// no clock, then every time control of TIME_CONTROLS and no clock again
+fn next_time_control(time_control: &Option<TimeControl>) -> Option<TimeControl> {
+    let controls: Vec<TimeControl> = TIME_CONTROLS.iter().filter_map(|text| TimeControl::parse(text).ok()).collect();
+    match time_control {
+        Some(time_control) => controls.iter().position(|control| control == time_control).and_then(|index| controls.get(index + 1)).cloned(),
+        None => controls.first().cloned()
+    }
+}
+
+// builds the dialog again whenever a choice changes
+#[allow(clippy::too_many_arguments)]
+fn new_game_dialog(commands: &mut Commands, dialog: ChangedRes<NewGameDialog>, engines: Res<Engines>, asset_server: Res<AssetServer>, button_materials: Res<ButtonMaterials>, mut color_materials: ResMut<Assets<ColorMaterial>>, dialog_query: Query<Entity, With<NewGameDialogNode>>) {
+    for entity in dialog_query.iter() {
+        commands.despawn_recursive(entity);
+    }
+    if !dialog.open {
+        return;
+    }
+
+    let font = asset_server.load("fonts/Menlo-Regular-01.ttf");
+    let row_material = color_materials.add(Color::NONE.into());
+    let text_style = TextStyle {
+        font_size: 20.,
+        color: Color::rgb(0.9, 0.9, 0.9),
+        ..Default::default()
+    };
+
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                left: Val::Px(10.),
+                top: Val::Px(60.),
+                ..Default::default()
+            },
+            // the rows go from the top to the bottom
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::FlexStart,
+            padding: Rect::all(Val::Px(5.)),
+            ..Default::default()
+        },
+        material: color_materials.add(Color::rgba(0., 0., 0., 0.8).into()),
+        ..Default::default()
+    }).with(NewGameDialogNode)
+    .with_children(|parent| {
+        spawn_menu_title(parent, font.clone(), "new game");
+
+        for color in [PieceColor::White, PieceColor::Black].iter() {
+            let config = dialog.players.get(*color);
+            parent.spawn(NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                material: row_material.clone(),
+                ..Default::default()
+            }).with_children(|parent| {
+                parent.spawn(TextBundle {
+                    text: Text {
+                        value: color_name(*color).to_string(),
+                        font: font.clone(),
+                        style: text_style.clone(),
+                    },
+                    style: Style {
+                        size: Size::new(Val::Px(70.), Val::Auto),
+                        margin: Rect::all(Val::Px(5.)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                });
+                spawn_small_button(parent, &button_materials, font.clone(), &player_text(config, &engines), NewGameDialogButton::Player(*color), 110., false);
+                // the computer and the engines have a strength, only the computer has a style
+                if config.player != Player::Human {
+                    let skill = config.skill();
+                    spawn_small_button(parent, &button_materials, font.clone(), &format!("{} ~{}", skill.name, skill.elo), NewGameDialogButton::Skill(*color), 220., false);
+                }
+                if config.player == Player::Computer {
+                    spawn_small_button(parent, &button_materials, font.clone(), config.style.name(), NewGameDialogButton::Style(*color), 130., false);
+                }
+            });
+        }
+
+        parent.spawn(NodeBundle {
+            style: Style {
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: row_material.clone(),
+            ..Default::default()
+        }).with_children(|parent| {
+            parent.spawn(TextBundle {
+                text: Text {
+                    value: "clock".to_string(),
+                    font: font.clone(),
+                    style: text_style.clone(),
+                },
+                style: Style {
+                    size: Size::new(Val::Px(70.), Val::Auto),
+                    margin: Rect::all(Val::Px(5.)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+            let label = dialog.time_control.as_ref().map_or_else(|| "none".to_string(), TimeControl::to_string);
+            spawn_small_button(parent, &button_materials, font.clone(), &label, NewGameDialogButton::TimeControl, 220., false);
+        });
+
+        parent.spawn(NodeBundle {
+            material: row_material.clone(),
+            ..Default::default()
+        }).with_children(|parent| {
+            spawn_button(parent, &button_materials, font.clone(), "start", NewGameDialogButton::Start);
+            spawn_button(parent, &button_materials, font.clone(), "cancel", NewGameDialogButton::Cancel);
+        });
+    });
+}
+
+fn new_game_dialog_buttons(mut dialog: ResMut<NewGameDialog>, mut players: ResMut<Players>, mut game_clock: ResMut<GameClock>, engines: Res<Engines>, mut new_game_events: ResMut<Events<NewGame>>, query: Query<(&Interaction, &NewGameDialogButton), Mutated<Interaction>>) {
+    for (interaction, button) in query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button {
+            NewGameDialogButton::Player(color) => {
+                let config = dialog.players.get_mut(*color);
+                config.player = next_player(config.player, &engines);
+            }
+            NewGameDialogButton::Skill(color) => {
+                let config = dialog.players.get_mut(*color);
+                config.skill = (config.skill + 1) % SKILLS.len();
+            }
+            NewGameDialogButton::Style(color) => {
+                let config = dialog.players.get_mut(*color);
+                let index = chess_core::Style::ALL.iter().position(|style| *style == config.style).unwrap_or(0);
+                config.style = chess_core::Style::ALL[(index + 1) % chess_core::Style::ALL.len()];
+            }
+            NewGameDialogButton::TimeControl => dialog.time_control = next_time_control(&dialog.time_control),
+            NewGameDialogButton::Start => {
+                dialog.open = false;
+                *players = dialog.players;
+                game_clock.control = dialog.time_control.clone();
+                new_game_events.send(NewGame);
+            }
+            NewGameDialogButton::Cancel => dialog.open = false,
+        }
+    }
+}